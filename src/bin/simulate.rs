@@ -0,0 +1,197 @@
+// Let a bot play many games without a window, and report how it did.
+//
+// Every game is written as one line of CSV, to standard output or to the file
+// given with --output, and a summary of all games is printed at the end. The
+// games are dealt from consecutive seeds, and the bots always play a deal the
+// same way, so runs can be repeated exactly and compared with each other.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use squaretillery::bot;
+use squaretillery::deck::DeckSpec;
+use squaretillery::game::{Action, Game, GameOutcome};
+use squaretillery::rules::RuleSet;
+use squaretillery::save;
+
+const USAGE: &str = "Usage: simulate [--bot NAME] [--deck SPEC] [--rules SPEC] [--games N] [--first-seed N] [--threads N] [--output FILE]";
+
+// The most moves a game may take before it is given up as unfinished. Bots can
+// keep sending the same cards back into the deck for ever.
+const MAX_MOVES: usize = 1000;
+
+struct Options {
+    bot: String,             // The bot that plays the games
+    deck: DeckSpec,          // The cards the games are dealt from
+    rules: RuleSet,          // The rules the games are played by
+    games: u64,              // How many games to play
+    first_seed: u64,         // The seed of the first game; the others follow it
+    threads: usize,          // How many games to play at the same time
+    output: Option<PathBuf>, // Where to write the CSV, instead of standard output
+}
+
+struct GameResult {
+    seed: u64,
+    outcome: Option<GameOutcome>,  // None if the game was given up
+    shame: u8,
+    royals_killed: u8,
+    moves: usize,
+    duration: Duration,
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut args = std::env::args().skip(1);
+    let threads = thread::available_parallelism().map(|threads| threads.get()).unwrap_or(1);
+    let mut options = Options { bot: "greedy".to_string(), deck: DeckSpec::STANDARD, rules: RuleSet::CLASSIC, games: 100, first_seed: 0, threads, output: None };
+    while let Some(arg) = args.next() {
+        let mut value = || { args.next().ok_or_else(|| format!("Missing value for {}.", arg)) };
+        match arg.as_str() {
+            "--bot" => {
+                let value = value()?;
+                if bot::by_name(&value).is_none() {
+                    return Err(format!("Unknown bot: {} (try {})", value, bot::NAMES.join(" or ")));
+                }
+                options.bot = value;
+            },
+            "--deck" => options.deck = save::parse_deck_spec(&value()?.split_whitespace().collect::<Vec<_>>())?,
+            "--rules" => options.rules = save::parse_rules(&value()?.split_whitespace().collect::<Vec<_>>())?,
+            "--games" => {
+                let value = value()?;
+                options.games = value.parse().map_err(|_| format!("Invalid number of games: {}", value))?;
+            },
+            "--first-seed" => {
+                let value = value()?;
+                options.first_seed = value.parse().map_err(|_| format!("Invalid seed: {}", value))?;
+            },
+            "--threads" => {
+                let value = value()?;
+                options.threads = value.parse().ok().filter(|threads| { *threads > 0 })
+                    .ok_or_else(|| format!("Invalid number of threads: {}", value))?;
+            },
+            "--output" => options.output = Some(PathBuf::from(value()?)),
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+// Deal the game with the given seed and let the bot play it to the end.
+fn play(seed: u64, bot_name: &str, deck: &DeckSpec, rules: RuleSet) -> GameResult {
+    let started = Instant::now();
+    let mut bot = bot::by_name(bot_name).expect("The bot should have been checked when parsing the options.");
+
+    let mut game = Game::with_deck(deck.clone(), seed)
+        .expect("The deck should have been checked when parsing the options.")
+        .with_rules(rules);
+    game.set_up().expect("The deck should contain enough cards to set up the board.");
+
+    let mut moves = 0;
+    while !game.is_game_over() && moves < MAX_MOVES {
+        let action = match game.drawn() {
+            Some(card) => bot.choose(&game, card),
+            None => Action::Draw,
+        };
+        if let Err(error) = game.apply(action) {
+            eprintln!("Seed {}: the {} bot tried an illegal move: {}", seed, bot.name(), error);
+            break;
+        }
+        moves += 1;
+    }
+
+    GameResult {
+        seed,
+        outcome: game.outcome(),
+        shame: game.get_shame(),
+        royals_killed: game.royals_killed(),
+        moves,
+        duration: started.elapsed(),
+    }
+}
+
+fn outcome_name(outcome: Option<GameOutcome>) -> &'static str {
+    match outcome {
+        Some(GameOutcome::Victory) => "victory",
+        Some(GameOutcome::Defeat) => "defeat",
+        Some(GameOutcome::Stuck) => "stuck",
+        None => "unfinished",
+    }
+}
+
+fn write_csv(results: &[GameResult], out: &mut dyn Write) -> io::Result<()> {
+    writeln!(out, "seed,outcome,shame,royals_killed,moves,duration_ms")?;
+    for result in results {
+        writeln!(out, "{},{},{},{},{},{}", result.seed, outcome_name(result.outcome), result.shame,
+                 result.royals_killed, result.moves, result.duration.as_millis())?;
+    }
+    Ok(())
+}
+
+// Summarise the results. Games that were given up have no outcome, so they
+// are only counted, and left out of everything else.
+fn print_summary(results: &[GameResult], bot_name: &str, duration: Duration) {
+    let finished: Vec<&GameResult> = results.iter().filter(|result| { result.outcome.is_some() }).collect();
+    let games = finished.len().max(1) as f64;
+    let count = |outcome| { finished.iter().filter(|result| { result.outcome == Some(outcome) }).count() };
+    let wins: Vec<&&GameResult> = finished.iter().filter(|result| { result.outcome == Some(GameOutcome::Victory) }).collect();
+    let perfect = wins.iter().filter(|result| { result.shame == 0 }).count();
+    let average = |total: usize, count: usize| { if count == 0 { 0.0 } else { total as f64 / count as f64 } };
+
+    eprintln!("{} games played by the {} bot in {:.1}s", results.len(), bot_name, duration.as_secs_f64());
+    eprintln!("  unfinished: {} (given up after {} moves, and not counted below)", results.len() - finished.len(), MAX_MOVES);
+    eprintln!("  finished:   {}", finished.len());
+    eprintln!("  victories:  {} ({:.1}%), of which perfect: {}", wins.len(), wins.len() as f64 / games * 100.0, perfect);
+    eprintln!("  defeats:    {}", count(GameOutcome::Defeat));
+    eprintln!("  stuck:      {}", count(GameOutcome::Stuck));
+    eprintln!("  average royals killed: {:.2}",
+              average(finished.iter().map(|result| { result.royals_killed as usize }).sum(), finished.len()));
+    eprintln!("  average shame in victories: {:.2}",
+              average(wins.iter().map(|result| { result.shame as usize }).sum(), wins.len()));
+    eprintln!("  average moves: {:.1}", average(finished.iter().map(|result| { result.moves }).sum(), finished.len()));
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("{}", USAGE);
+            std::process::exit(1);
+        },
+    };
+
+    // Each thread plays every nth seed and sends back the results as they come
+    let started = Instant::now();
+    let (sender, receiver) = mpsc::channel();
+    let workers: Vec<_> = (0..options.threads as u64).map(|worker| {
+        let sender = sender.clone();
+        let (bot_name, deck, rules, first_seed, games, threads) =
+            (options.bot.clone(), options.deck.clone(), options.rules, options.first_seed, options.games, options.threads as u64);
+        thread::spawn(move || {
+            for game in (worker..games).step_by(threads as usize) {
+                if sender.send(play(first_seed.wrapping_add(game), &bot_name, &deck, rules)).is_err() { break; }
+            }
+        })
+    }).collect();
+    drop(sender);
+
+    let mut results: Vec<GameResult> = receiver.iter().collect();
+    for worker in workers {
+        worker.join().expect("A simulation thread panicked.");
+    }
+    results.sort_by_key(|result| { result.seed.wrapping_sub(options.first_seed) });
+
+    let written = match &options.output {
+        Some(path) => File::create(path).and_then(|mut file| { write_csv(&results, &mut file) }),
+        None => write_csv(&results, &mut io::stdout().lock()),
+    };
+    if let Err(error) = written {
+        eprintln!("Could not write results: {}", error);
+        std::process::exit(1);
+    }
+
+    print_summary(&results, &options.bot, started.elapsed());
+}
@@ -0,0 +1,296 @@
+// Play the game in a terminal, without a window.
+//
+// The board is printed as a grid with columns a to e and rows 1 to 5, and moves
+// are entered as commands, one per line, so games can also be scripted by
+// piping commands into the program:
+//
+//     draw
+//     place b3
+//     shame
+
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+use squaretillery::{bot, daily, notation, puzzle, save, stats};
+use squaretillery::deck::DeckSpec;
+use squaretillery::game::{Action, BoardPosition, Game, GameOutcome};
+use squaretillery::puzzle::{GoalStatus, Puzzle};
+use squaretillery::rules::RuleSet;
+
+const USAGE: &str = "Usage: terminal [--seed N] [--deck SPEC] [--rules SPEC] | --position TEXT | --puzzle FILE | --daily";
+
+const HELP: &str = "Commands:
+  draw          draw a card
+  place TILE    place the drawn card on a tile, such as b3
+  shame         put the drawn card on the shame pile
+  undo, redo    take back a move, or make it again
+  hint          ask what to do with the drawn card
+  board         show the board again
+  position      print the position, to load it again with --position
+  retry         start the puzzle again
+  stats         show statistics about the games played so far
+  help          show this list
+  quit          stop playing";
+
+const COLUMNS: &str = "abcde";
+
+struct Options {
+    seed: Option<u64>,      // Deal the game from this seed instead of a random one
+    deck: Option<DeckSpec>, // The cards the game is dealt from, if not a standard deck
+    rules: Option<RuleSet>, // The rules the game is played by, if not the classic ones
+    position: Option<Game>, // Play from this position instead of dealing a new game
+    puzzle: Option<Puzzle>, // Solve this puzzle instead of playing a game
+    daily: bool,            // Play today's daily challenge
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut args = std::env::args().skip(1);
+    let mut options = Options { seed: None, deck: None, rules: None, position: None, puzzle: None, daily: false };
+    while let Some(arg) = args.next() {
+        let mut value = || { args.next().ok_or_else(|| format!("Missing value for {}.", arg)) };
+        match arg.as_str() {
+            "--seed" => {
+                let value = value()?;
+                options.seed = Some(value.parse().map_err(|_| format!("Invalid seed: {}", value))?);
+            },
+            "--deck" => options.deck = Some(save::parse_deck_spec(&value()?.split_whitespace().collect::<Vec<_>>())?),
+            "--rules" => options.rules = Some(save::parse_rules(&value()?.split_whitespace().collect::<Vec<_>>())?),
+            "--position" => options.position = Some(notation::parse_position(&value()?)?),
+            "--daily" => options.daily = true,
+            "--puzzle" => {
+                let value = value()?;
+                options.puzzle = Some(puzzle::load(Path::new(&value)).map_err(|error| format!("Could not load puzzle: {}", error))?);
+            },
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+
+    // The daily challenge is the same deal for everyone, so it cannot be changed
+    let conflicting = [
+        ("--seed", options.seed.is_some()), ("--deck", options.deck.is_some()), ("--rules", options.rules.is_some()),
+        ("--position", options.position.is_some()), ("--puzzle", options.puzzle.is_some()),
+    ];
+    if let Some((name, _)) = conflicting.iter().find(|(_, given)| { options.daily && *given }) {
+        return Err(format!("The daily challenge cannot be played with {}.", name));
+    }
+    Ok(options)
+}
+
+// Parse a tile such as "b3", where the letter is the column and the number is
+// the row, counting from the top left.
+fn parse_tile(text: &str) -> Option<BoardPosition> {
+    let mut chars = text.chars();
+    let x = COLUMNS.find(chars.next()?.to_ascii_lowercase())? as i8 - 2;
+    let y = chars.as_str().parse::<i8>().ok()?.checked_sub(3)?;
+    BoardPosition::new((x, y)).ok()
+}
+
+fn tile_name(pos: BoardPosition) -> String {
+    format!("{}{}", COLUMNS.as_bytes()[(pos.x() + 2) as usize] as char, pos.y() + 3)
+}
+
+fn print_board(game: &Game) {
+    println!();
+    println!("    {}", COLUMNS.chars().map(|column| { format!("{:<7}", column) }).collect::<String>().trim_end());
+    for y in -2..=2 {
+        let mut line = format!("{:<4}", y + 3);
+        for x in -2..=2 {
+            let cell = match BoardPosition::new((x, y)) {
+                Err(_) => String::new(),  // Corners are not part of the board
+                Ok(pos) => match game.get_card_at(pos) {
+                    None => ".".to_string(),
+                    Some(card) if game.get_armor_at(pos) > 0 => format!("{}+{}", card, game.get_armor_at(pos)),
+                    Some(card) => card.to_string(),
+                },
+            };
+            line.push_str(&format!("{:<7}", cell));
+        }
+        println!("{}", line.trim_end());
+    }
+    println!();
+
+    let drawn = game.drawn().map(|card| { card.to_string() }).unwrap_or_else(|| "-".to_string());
+    println!("Drawn: {}   Left: {}   Shame: {}   Killed: {}/{}", drawn, game.cards_left(), game.get_shame(),
+             game.royals_killed(), game.royals_total());
+}
+
+// Make a move and describe what happened. Returns false if the move is not
+// allowed.
+fn apply(game: &mut Game, action: Action) -> bool {
+    match game.apply(action) {
+        Ok(outcome) => {
+            if let Some(card) = game.drawn().filter(|_| { action == Action::Draw }) {
+                println!("Drew {}.", card);
+            }
+            for (pos, royal) in outcome.killed {
+                println!("Killed {} at {}.", royal, tile_name(pos));
+            }
+            for pos in outcome.recycled {
+                println!("The pile at {} went back into the deck.", tile_name(pos));
+            }
+            true
+        },
+        Err(error) => {
+            eprintln!("{}", error);
+            false
+        },
+    }
+}
+
+fn main() {
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("{}", USAGE);
+            std::process::exit(1);
+        },
+    };
+
+    let today = daily::Date::today();
+    if options.daily {
+        if let Some(result) = daily::records_path().and_then(|path| { daily::attempt(&path, today) }) {
+            println!("You have already played the daily challenge for {}: {}", today, result);
+            return;
+        }
+    }
+
+    // Games played from a given position or puzzle do not count in the
+    // statistics; puzzles never get as far as recording them
+    let counts_in_stats = options.position.is_none();
+    let mut game = match (&options.puzzle, options.position) {
+        (Some(puzzle), _) => {
+            println!("{}: {}. Type help for a list of commands.", puzzle.name, puzzle.goal);
+            puzzle.start.clone()
+        },
+        (None, Some(game)) => {
+            println!("Playing from the given position. Type help for a list of commands.");
+            game
+        },
+        (None, None) if options.daily => {
+            println!("Playing the daily challenge for {}. Type help for a list of commands.", today);
+            today.game()
+        },
+        (None, None) => {
+            let mut game = Game::with_deck(options.deck.unwrap_or_default(), options.seed.unwrap_or_else(rand::random))
+                .expect("The deck should have been checked when parsing the options.")
+                .with_rules(options.rules.unwrap_or_default());
+            game.set_up().expect("The deck should contain enough cards to set up the board.");
+            println!("Playing seed {}. Type help for a list of commands.", game.seed());
+            game
+        },
+    };
+    print_board(&game);
+
+    let stdin = io::stdin();
+    loop {
+        print!("> ");
+        io::stdout().flush().ok();
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 { break; }
+        let words: Vec<&str> = line.split_whitespace().collect();
+
+        let changed = match words.as_slice() {
+            [] => false,
+            ["draw"] => apply(&mut game, Action::Draw),
+            ["place", tile] => match parse_tile(tile) {
+                Some(pos) => apply(&mut game, Action::Place(pos)),
+                None => { eprintln!("Not a tile: {}", tile); false },
+            },
+            ["shame"] => apply(&mut game, Action::Shame),
+            ["undo"] | ["redo"] if options.daily => { eprintln!("Moves cannot be taken back in the daily challenge."); false },
+            ["undo"] => game.undo().is_some() || { eprintln!("Nothing to undo."); false },
+            ["redo"] => game.redo().is_some() || { eprintln!("Nothing to redo."); false },
+            ["hint"] if options.daily => { eprintln!("There are no hints in the daily challenge."); false },
+            ["hint"] => {
+                match bot::hint(&game, &mut bot::Greedy, tile_name) {
+                    Some(bot::Hint { action: Action::Place(pos), reason }) => println!("Place it at {}: {}.", tile_name(pos), reason),
+                    Some(bot::Hint { reason, .. }) => println!("Shame it: {}.", reason),
+                    None => println!("Draw a card first."),
+                }
+                false
+            },
+            ["board"] => true,
+            ["position"] => { println!("{}", notation::write_position(&game)); false },
+            ["stats"] => {
+                match stats::path().map(|path| { stats::load(&path) }) {
+                    Some(Ok(stats)) => stats.lines().iter().for_each(|line| { println!("{}", line) }),
+                    Some(Err(error)) => eprintln!("Could not load statistics: {}", error),
+                    None => eprintln!("There is nowhere to keep statistics."),
+                }
+                false
+            },
+            ["retry"] => match &options.puzzle {
+                Some(puzzle) => { game = puzzle.start.clone(); true },
+                None => { eprintln!("There is no puzzle to retry."); false },
+            },
+            ["help"] => { println!("{}", HELP); false },
+            ["quit"] | ["exit"] => break,
+            _ => { eprintln!("Unknown command: {} (type help for a list)", line.trim()); false },
+        };
+        if !changed { continue; }
+        print_board(&game);
+
+        if let Some(puzzle) = &options.puzzle {
+            match puzzle.status(&game) {
+                GoalStatus::Met => { println!("Solved!"); break; },
+                GoalStatus::Failed => println!("The goal was to {}. Type retry to try again.", puzzle.goal),
+                GoalStatus::InProgress => {},
+            }
+            continue;
+        }
+
+        if let Some(outcome) = game.outcome() {
+            let title = match outcome {
+                GameOutcome::Victory => "Victory!",
+                GameOutcome::Defeat => "Out of cards.",
+                GameOutcome::Stuck => "No moves left.",
+            };
+            let score = game.score();
+            println!("{} {}", title, score.summary());
+            for line in score.breakdown() {
+                println!("  {}", line);
+            }
+            if let Some(Err(error)) = stats::path().filter(|_| { counts_in_stats }).map(|path| { stats::record(&path, &score) }) {
+                eprintln!("Could not record statistics: {}", error);
+            }
+            break;
+        }
+    }
+
+    // The daily challenge is over once the player stops, whether or not the
+    // game was finished
+    if options.daily {
+        if let Some(path) = daily::records_path() {
+            if let Err(error) = daily::record(&path, today, &game.score()) {
+                eprintln!("Could not record the daily challenge: {}", error);
+            }
+        }
+        println!("{}", daily::result_line(today, &game.score()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiles_are_named_by_column_and_row() {
+        let b3 = BoardPosition::new((-1, 0)).unwrap();
+        assert_eq!(parse_tile("b3"), Some(b3));
+        assert_eq!(parse_tile("B3"), Some(b3));
+        assert_eq!(tile_name(b3), "b3");
+        assert_eq!(parse_tile("A2"), BoardPosition::new((-2, -1)).ok());
+        for pos in BoardPosition::all_valid() {
+            assert_eq!(parse_tile(&tile_name(pos)), Some(pos));
+        }
+    }
+
+    #[test]
+    fn tiles_off_the_board_are_rejected() {
+        for text in &["A1", "f1", "a0", "a-128", "a", "", "3b"] {
+            assert_eq!(parse_tile(text), None, "{}", text);
+        }
+    }
+}
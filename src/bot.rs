@@ -0,0 +1,188 @@
+// Bots that play the game on their own.
+//
+// A bot is anything that implements `Strategy`: it is shown the game and the
+// card that was drawn, and decides where to place the card or whether to shame
+// it. Drawing is always the only thing to do when no card is drawn, so bots are
+// not asked about it.
+
+use crate::game::{Action, BoardPosition, Card, Game};
+use crate::random::{self, SplitMix64};
+use crate::solver::promise;
+
+// The longest game that a Monte Carlo rollout plays before it is scored.
+const MAX_ROLLOUT_MOVES: usize = 300;
+
+pub trait Strategy {
+    fn name(&self) -> &'static str;
+
+    // Decide what to do with the drawn card. The action must be legal.
+    fn choose(&mut self, game: &Game, drawn: Card) -> Action;
+}
+
+// Return the bot with the given name, or None if there is no such bot.
+pub fn by_name(name: &str) -> Option<Box<dyn Strategy + Send>> {
+    match name {
+        "greedy" => Some(Box::new(Greedy)),
+        "montecarlo" => Some(Box::new(MonteCarlo::new(20))),
+        _ => None,
+    }
+}
+
+pub const NAMES: &[&str] = &["greedy", "montecarlo"];
+
+// A move that a bot recommends, and why.
+#[derive(Debug, Clone)]
+pub struct Hint {
+    pub action: Action,
+    pub reason: String,  // For example "kills the Jack at the middle of the right edge"
+}
+
+// Ask the bot what to do with the drawn card, and explain what the move would
+// achieve, naming tiles the way the front end does. Returns None if no card is
+// drawn.
+pub fn hint(game: &Game, strategy: &mut dyn Strategy, name: fn(BoardPosition) -> String) -> Option<Hint> {
+    let action = strategy.choose(game, game.drawn()?);
+    let mut next = game.clone();
+    next.forget_history();
+    let outcome = next.perform(action).ok()?;
+
+    let reason = if !outcome.killed.is_empty() {
+        let killed: Vec<String> = outcome.killed.iter().map(|(pos, royal)| { describe_royal(*royal, name(*pos)) }).collect();
+        format!("kills {}", killed.join(" and "))
+    } else if let Some(pos) = outcome.royal_placed {
+        let royal = royal_name(game.drawn()?);
+        let places = game.legal_actions().len() - 1;  // Not counting shame
+        if places > 1 { format!("the {} is easiest to kill at {}", royal, name(pos)) }
+        else { format!("the {} can only go at {}", royal, name(pos)) }
+    } else if let Some((pos, _)) = outcome.armor_added {
+        format!("armors {} rather than shaming the card", describe_royal(next.get_card_at(pos)?, name(pos)))
+    } else if let Some(pos) = outcome.recycled.first() {
+        format!("sends the pile at {} back into the deck", name(*pos))
+    } else if let Action::Place(pos) = action {
+        let target = next.royal_statuses().into_iter().find(|status| { status.position.cannon_towards().contains(&pos) });
+        match target {
+            Some(status) => format!("loads the cannon aimed at {}", describe_royal(status.card, name(status.position))),
+            None => "keeps the card out of the shame pile".to_string(),
+        }
+    } else if game.legal_actions().len() == 1 {
+        "the card cannot be placed anywhere".to_string()
+    } else {
+        "no placement helps, so shame the card".to_string()
+    };
+    Some(Hint { action, reason })
+}
+
+// Makes whichever move leaves the position that looks best right away.
+pub struct Greedy;
+
+impl Strategy for Greedy {
+    fn name(&self) -> &'static str { "greedy" }
+
+    fn choose(&mut self, game: &Game, _drawn: Card) -> Action {
+        best_by(game, promise)
+    }
+}
+
+// Tries each move against a number of random orders of the cards that are
+// still in the deck, playing each one out greedily, and makes the move that
+// did best on average. The orders only depend on the seed of the game and the
+// number of moves made, so the bot always plays the same deal the same way.
+pub struct MonteCarlo {
+    samples: usize,  // The number of deck orders that each move is tried with
+    rng: SplitMix64,
+}
+
+impl MonteCarlo {
+    pub fn new(samples: usize) -> MonteCarlo {
+        MonteCarlo { samples, rng: SplitMix64::new(0) }
+    }
+
+    // Play the game out with the greedy bot and score the end result.
+    fn rollout(&mut self, mut game: Game) -> i32 {
+        game.shuffle_deck(&mut self.rng);
+        for _ in 0..MAX_ROLLOUT_MOVES {
+            if game.is_game_over() { break; }
+            let action = if game.drawn().is_some() { best_by(&game, promise) } else { Action::Draw };
+            if game.perform(action).is_err() { break; }
+        }
+        promise(&game)
+    }
+}
+
+impl Strategy for MonteCarlo {
+    fn name(&self) -> &'static str { "montecarlo" }
+
+    fn choose(&mut self, game: &Game, _drawn: Card) -> Action {
+        self.rng = SplitMix64::new(random::mix(game.seed().wrapping_add(game.history().len() as u64)));
+        let mut start = game.clone();
+        start.forget_history();
+
+        let mut best = (i32::MIN, Action::Shame);
+        for legal in start.legal_actions() {
+            let mut next = start.clone();
+            if next.perform(legal.action()).is_err() { continue; }
+            let total: i32 = (0..self.samples).map(|_| { self.rollout(next.clone()) }).sum();
+            if total > best.0 { best = (total, legal.action()); }
+        }
+        best.1
+    }
+}
+
+fn royal_name(royal: Card) -> &'static str {
+    match royal.value() {
+        11 => "Jack",
+        12 => "Queen",
+        _ => "King",
+    }
+}
+
+// Name a royal along with where it is, such as "the Queen at the middle of the
+// top edge".
+fn describe_royal(royal: Card, place: String) -> String {
+    format!("the {} at {}", royal_name(royal), place)
+}
+
+// Return the legal action that leads to the position with the highest value.
+fn best_by(game: &Game, value: fn(&Game) -> i32) -> Action {
+    let mut start = game.clone();
+    start.forget_history();
+
+    let mut best = (i32::MIN, Action::Shame);
+    for legal in start.legal_actions() {
+        let mut next = start.clone();
+        if next.perform(legal.action()).is_err() { continue; }
+        let value = value(&next);
+        if value > best.0 { best = (value, legal.action()); }
+    }
+    best.1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::parse_position;
+
+    #[test]
+    fn hint_names_tiles_like_the_front_end() {
+        let game = parse_position("-,-,-/-,-,-,-,-/-,-,5H,6C,JS/-,-,-,-,-/-,-,- 4D 2C - 0 12 classic").unwrap();
+        let described = hint(&game, &mut Greedy, |pos| { pos.to_string() }).unwrap();
+        assert_eq!(described.action, Action::Place(BoardPosition::new((-1, 0)).unwrap()));
+        assert_eq!(described.reason, "kills the Jack at the middle of the right edge");
+
+        let numbered = hint(&game, &mut Greedy, |pos| { format!("{}{}", pos.x(), pos.y()) }).unwrap();
+        assert_eq!(numbered.reason, "kills the Jack at 20");
+    }
+
+    #[test]
+    fn monte_carlo_plays_a_deal_the_same_way_every_time() {
+        let mut game = Game::with_seed(5);
+        game.set_up().unwrap();
+        for _ in 0..6 {
+            game.apply(Action::Draw).unwrap();
+            let drawn = game.drawn().unwrap();
+            let action = MonteCarlo::new(3).choose(&game, drawn);
+            assert_eq!(MonteCarlo::new(3).choose(&game, drawn), action);
+            game.apply(action).unwrap();
+        }
+    }
+}
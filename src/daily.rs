@@ -0,0 +1,139 @@
+// The daily challenge: one deal per day, the same for everyone.
+//
+// The deal is dealt from a seed that is worked out from the date alone, using
+// the same seeded shuffle as any other game, so every machine gets the same
+// cards on the same day. Days follow UTC, so that players in different time
+// zones share a challenge. Each player gets one attempt a day, and its result
+// is recorded in `daily.txt` in the data directory, one line per day:
+//
+//     2026-10-17 WON WITH 2 SHAME
+//
+// The result can be shared as a single line, such as "Squaretillery daily
+// 2026-10-17: WON WITH 2 SHAME".
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::game::Game;
+use crate::paths;
+use crate::random;
+use crate::score::Score;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Date {
+    year: i32,
+    month: u8,
+    day: u8,
+}
+
+impl Date {
+    // The current date in UTC.
+    pub fn today() -> Date {
+        let seconds = SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+        Date::from_days_since_epoch((seconds / SECONDS_PER_DAY) as i64)
+    }
+
+    // Convert a number of days since 1970-01-01 to a date in the Gregorian
+    // calendar. See http://howardhinnant.github.io/date_algorithms.html.
+    fn from_days_since_epoch(days: i64) -> Date {
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let month_from_march = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * month_from_march + 2) / 5 + 1) as u8;
+        let month = if month_from_march < 10 { month_from_march + 3 } else { month_from_march - 9 } as u8;
+        let year = (year_of_era + era * 400) as i32 + if month <= 2 { 1 } else { 0 };
+        Date { year, month, day }
+    }
+
+    // The seed of the deal for this date. The date is mixed with SplitMix64,
+    // so that consecutive days get seeds that have nothing to do with each
+    // other.
+    pub fn seed(&self) -> u64 {
+        random::mix(self.year as u64 * 10_000 + self.month as u64 * 100 + self.day as u64)
+    }
+
+    // Deal the challenge for this date, with the standard deck and rules.
+    pub fn game(&self) -> Game {
+        let mut game = Game::with_seed(self.seed());
+        game.set_up().expect("The deck should contain enough cards to set up the board.");
+        game
+    }
+}
+
+// Dates are written as YYYY-MM-DD.
+impl fmt::Display for Date {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+// A line that tells others how the challenge went, without giving away the
+// deal.
+pub fn result_line(date: Date, score: &Score) -> String {
+    format!("Squaretillery daily {}: {}", date, score.summary())
+}
+
+// The file that the results of attempts are recorded in.
+pub fn records_path() -> Option<PathBuf> {
+    paths::data_dir().map(|dir| dir.join("daily.txt"))
+}
+
+// Return the result of the attempt at the challenge for the given date, if it
+// was already played.
+pub fn attempt(path: &Path, date: Date) -> Option<String> {
+    let text = fs::read_to_string(path).ok()?;
+    let date = date.to_string();
+    text.lines().find_map(|line| {
+        let mut parts = line.splitn(2, ' ');
+        if parts.next() == Some(date.as_str()) { Some(parts.next().unwrap_or("").to_string()) } else { None }
+    })
+}
+
+// Record the result of the attempt at the challenge for the given date.
+pub fn record(path: &Path, date: Date, score: &Score) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut text = fs::read_to_string(path).unwrap_or_default();
+    text.push_str(&format!("{} {}\n", date, score.summary()));
+    fs::write(path, text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u8, day: u8) -> Date {
+        Date { year, month, day }
+    }
+
+    #[test]
+    fn days_are_counted_from_the_epoch() {
+        assert_eq!(Date::from_days_since_epoch(0), date(1970, 1, 1));
+        assert_eq!(Date::from_days_since_epoch(-1), date(1969, 12, 31));
+        assert_eq!(Date::from_days_since_epoch(20_743), date(2026, 10, 17));
+    }
+
+    #[test]
+    fn leap_days_follow_the_gregorian_calendar() {
+        assert_eq!(Date::from_days_since_epoch(19_782), date(2024, 2, 29));
+        assert_eq!(Date::from_days_since_epoch(19_783), date(2024, 3, 1));
+        assert_eq!(Date::from_days_since_epoch(11_016), date(2000, 2, 29));  // Divisible by 400
+        assert_eq!(Date::from_days_since_epoch(47_540), date(2100, 2, 28));  // Divisible by 100 only
+        assert_eq!(Date::from_days_since_epoch(47_541), date(2100, 3, 1));
+    }
+
+    #[test]
+    fn each_day_has_its_own_seed() {
+        assert_eq!(date(2026, 1, 5).to_string(), "2026-01-05");
+        assert_eq!(date(2026, 10, 17).seed(), date(2026, 10, 17).seed());
+        assert_ne!(date(2026, 10, 17).seed(), date(2026, 10, 18).seed());
+    }
+}
@@ -0,0 +1,154 @@
+// The cards that a game is dealt from.
+//
+// A `DeckSpec` says how many standard decks are shuffled together, how many
+// jokers are added, and which values and suits are left out. `STANDARD` is a
+// single deck with two jokers, which is what Gridcannon is normally played
+// with; the other presets make the game easier or longer.
+
+use crate::game::{Card, Suit};
+
+const SUITS: [Suit; 4] = [Suit::Spades, Suit::Hearts, Suit::Clubs, Suit::Diamonds];
+
+// Shuffling more decks than this together makes more royals than a game can
+// keep track of.
+pub const MAX_DECKS: u8 = 4;
+
+// The fewest cards other than royals that a deck needs, so that every cannon
+// tile around the centre can be dealt a card when the game is set up.
+const MIN_CANNON_CARDS: usize = 8;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DeckSpec {
+    pub decks: u8,                  // How many standard decks of 52 cards are shuffled together
+    pub jokers: u8,                 // How many jokers are added in total, not per deck
+    pub excluded_values: Vec<u8>,   // Values from 1 to 13 that are taken out of every deck
+    pub excluded_suits: Vec<Suit>,  // Suits that are taken out of every deck
+}
+
+impl DeckSpec {
+    pub const STANDARD: DeckSpec = DeckSpec { decks: 1, jokers: 2, excluded_values: Vec::new(), excluded_suits: Vec::new() };
+
+    // Without jokers, there is no way to clear the cannon other than aces.
+    pub const NO_JOKERS: DeckSpec = DeckSpec { decks: 1, jokers: 0, excluded_values: Vec::new(), excluded_suits: Vec::new() };
+
+    // Two decks with four jokers, which makes for twenty-four royals to kill.
+    pub const MARATHON: DeckSpec = DeckSpec { decks: 2, jokers: 4, excluded_values: Vec::new(), excluded_suits: Vec::new() };
+
+    pub const PRESETS: &'static [(&'static str, DeckSpec)] = &[
+        ("standard", DeckSpec::STANDARD),
+        ("no-jokers", DeckSpec::NO_JOKERS),
+        ("marathon", DeckSpec::MARATHON),
+    ];
+
+    // Return the preset with the given name, or None if there is no such preset.
+    pub fn preset(name: &str) -> Option<DeckSpec> {
+        DeckSpec::PRESETS.iter().find(|(preset, _)| { *preset == name }).map(|(_, spec)| { spec.clone() })
+    }
+
+    pub fn preset_names() -> Vec<&'static str> {
+        DeckSpec::PRESETS.iter().map(|(name, _)| { *name }).collect()
+    }
+
+    // Return the name of the preset that this deck is, if any.
+    pub fn preset_name(&self) -> Option<&'static str> {
+        DeckSpec::PRESETS.iter().find(|(_, spec)| { spec == self }).map(|(name, _)| { *name })
+    }
+
+    // Every card in the deck, before it is shuffled.
+    pub fn cards(&self) -> Vec<Card> {
+        let mut cards = Vec::new();
+        for _ in 0..self.decks {
+            for &suit in &SUITS {
+                if self.excluded_suits.contains(&suit) { continue }
+                for value in 1..(13 + 1) {
+                    if self.excluded_values.contains(&value) { continue }
+                    cards.push(Card::new(value, suit).unwrap());
+                }
+            }
+        }
+        for _ in 0..self.jokers {
+            cards.push(Card::new(0, Suit::Joker).unwrap());
+        }
+        cards
+    }
+
+    // The number of royals in the deck, all of which must be killed to win.
+    // Decks with too many to count, which `check` rejects, give the most a u8
+    // can hold.
+    pub fn royals(&self) -> u8 {
+        let suits = SUITS.iter().filter(|suit| { !self.excluded_suits.contains(suit) }).count() as u8;
+        let values = (11..=13).filter(|value| { !self.excluded_values.contains(value) }).count() as u8;
+        self.decks.saturating_mul(suits).saturating_mul(values)
+    }
+
+    // Check that a game can be played with the deck: it needs at least one
+    // royal, and enough other cards to set up the board.
+    pub fn check(&self) -> Result<(), String> {
+        if self.decks == 0 || self.decks > MAX_DECKS {
+            return Err(format!("the number of decks must be between 1 and {}", MAX_DECKS));
+        }
+        if let Some(value) = self.excluded_values.iter().find(|value| { !(1..=13).contains(*value) }) {
+            return Err(format!("there are no cards with value {}", value));
+        }
+        if self.excluded_suits.contains(&Suit::Joker) {
+            return Err("jokers are left out by setting their number to 0".to_string());
+        }
+
+        if self.royals() == 0 {
+            return Err("the deck has no royals to kill".to_string());
+        }
+        if self.cards().iter().filter(|card| { !card.is_royal() }).count() < MIN_CANNON_CARDS {
+            return Err(format!("the deck needs at least {} cards that are not royals", MIN_CANNON_CARDS));
+        }
+        Ok(())
+    }
+}
+
+impl Default for DeckSpec {
+    fn default() -> DeckSpec {
+        DeckSpec::STANDARD
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_found_by_name() {
+        for name in DeckSpec::preset_names() {
+            assert_eq!(DeckSpec::preset(name).unwrap().preset_name(), Some(name));
+        }
+        assert_eq!(DeckSpec::preset("tarot"), None);
+    }
+
+    #[test]
+    fn royals_are_counted_for_every_deck() {
+        assert_eq!(DeckSpec::STANDARD.royals(), 12);
+        assert_eq!(DeckSpec::MARATHON.royals(), 24);
+        let spec = DeckSpec { excluded_values: vec![13], excluded_suits: vec![Suit::Hearts], ..DeckSpec::MARATHON };
+        assert_eq!(spec.royals(), 2 * 3 * 2);
+        assert_eq!(spec.royals(), spec.cards().iter().filter(|card| { card.is_royal() }).count() as u8);
+        assert_eq!(DeckSpec::STANDARD.cards().len(), 54);
+    }
+
+    #[test]
+    fn presets_are_playable() {
+        for (_, spec) in DeckSpec::PRESETS {
+            assert_eq!(spec.check(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn unplayable_decks_are_rejected() {
+        let check = |spec: DeckSpec| { spec.check().is_err() };
+        assert!(check(DeckSpec { decks: 0, ..DeckSpec::STANDARD }));
+        assert!(check(DeckSpec { decks: 100, ..DeckSpec::STANDARD }));  // Too many royals to count
+        assert_eq!(DeckSpec { decks: 100, ..DeckSpec::STANDARD }.royals(), u8::MAX);
+        assert!(check(DeckSpec { excluded_values: vec![14], ..DeckSpec::STANDARD }));
+        assert!(check(DeckSpec { excluded_suits: vec![Suit::Joker], ..DeckSpec::STANDARD }));
+        assert!(check(DeckSpec { excluded_values: vec![11, 12, 13], ..DeckSpec::STANDARD }));
+        assert!(check(DeckSpec { excluded_values: (1..=10).collect(), ..DeckSpec::NO_JOKERS }));
+        assert!(!check(DeckSpec { decks: MAX_DECKS, ..DeckSpec::STANDARD }));
+    }
+}
@@ -1,3 +1,46 @@
+use std::cell::Cell;
+use std::fmt;
+use std::str::FromStr;
+
+use crate::deck::DeckSpec;
+use crate::random::SplitMix64;
+use crate::rules::{RoyalPlacement, RuleSet, Stacking};
+use crate::score::Score;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum GameError {
+    InvalidCard { value: u8, suit: Suit },
+    InvalidNotation(String),
+    OffBoard((i8, i8)),
+    Corner((i8, i8)),
+    DeckEmpty,
+    AlreadyDrawn,
+    NothingDrawn,
+    IllegalPlacement { reason: String },
+    InvalidDeck(String),
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use GameError::*;
+        match self {
+            InvalidCard { value, suit: Suit::Joker } => write!(f, "Jokers must have value 0, not {}.", value),
+            InvalidCard { value: 0, suit } => write!(f, "Only jokers can have value 0, not {:?}.", suit),
+            InvalidCard { value, .. } => write!(f, "Card value cannot be greater than 13, but was {}.", value),
+            InvalidNotation(text) => write!(f, "{:?} is not a card: cards are written as a value (A, 2-10, J, Q, K) and a suit (S, H, C, D), or JK for a joker.", text),
+            OffBoard(coordinates) => write!(f, "{:?} is an invalid coordinate: coordinates must be between -2 and 2.", coordinates),
+            Corner(coordinates) => write!(f, "{:?} is an invalid coordinate: there are no cards in the corners of the board.", coordinates),
+            DeckEmpty => write!(f, "Tried to draw card when there was no more cards in the deck."),
+            AlreadyDrawn => write!(f, "Cannot draw card while one is already drawn."),
+            NothingDrawn => write!(f, "No card is drawn."),
+            IllegalPlacement { reason } => write!(f, "Cannot place card: {}.", reason),
+            InvalidDeck(reason) => write!(f, "Cannot deal from this deck: {}.", reason),
+        }
+    }
+}
+
+impl std::error::Error for GameError {}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Suit {
     Spades,
@@ -16,36 +59,141 @@ impl Suit {
     }
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Card {
     suit: Suit,
     value: u8,  // NOTE: Not used when suit is Joker.
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub struct Pile {
     cards: Vec<Card>,  // NOTE: The top of the pile is at the back.
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Board {
     stacks: [[Pile; 5]; 5],
     armor: [[u8; 5]; 5],     // TODO: This is an inelegant representation. Should this even be here, or should it be somewhere else?
 }
 
-#[derive(Debug)]
+// What the cards on a cannon must have in common with a royal to kill it.
+// Which royals need what is decided by the rules.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Requirement {
+    Any,        // Cards of any suit will do
+    SameColor,  // Only cards of the same colour as the royal
+    SameSuit,   // Only cards of the same suit as the royal
+}
+
+// Everything there is to know about a royal on the edge of the board.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct RoyalStatus {
+    pub position: BoardPosition,
+    pub card: Card,
+    pub armor: u8,
+    pub damage: u8,                    // Damage that its cannon would currently deal
+    pub requirement: Requirement,
+    pub triggers: Vec<BoardPosition>,  // Outer cannon positions that fire the cannon at it
+}
+
+// How an attack on a royal would turn out, worked out without changing the
+// board.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct AttackPreview {
+    pub royal: BoardPosition,
+    pub card: Card,      // The royal being attacked
+    pub damage: u8,      // Sum of the values of the cards on the cannon
+    pub health: u8,      // Value of the royal plus its armor
+    pub suit_ok: bool,   // Whether the cannon meets the requirement for the royal
+    pub dies: bool,      // Whether the royal would be killed, which also needs a card on both cannon tiles
+}
+
+// Everything a player can do on their turn. Placing a card covers stacking on
+// the cannon, adding armor to a royal and placing a royal on the edge; which
+// one happens depends on the drawn card and the target position.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Action {
+    Draw,
+    Place(BoardPosition),
+    Shame,
+}
+
+// A legal action for the current state of a game. Unlike Action, this
+// distinguishes between the different kinds of placement.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LegalAction {
+    Draw,
+    Stack(BoardPosition),  // Place the drawn card on the cannon
+    Armor(BoardPosition),  // Add the drawn card as armor to a royal
+    Royal(BoardPosition),  // Place the drawn royal on the edge
+    Shame,
+}
+
+impl LegalAction {
+    pub fn action(self) -> Action {
+        match self {
+            LegalAction::Draw => Action::Draw,
+            LegalAction::Stack(pos) | LegalAction::Armor(pos) | LegalAction::Royal(pos) => Action::Place(pos),
+            LegalAction::Shame => Action::Shame,
+        }
+    }
+}
+
+// What happened as a result of applying an action.
+#[derive(Debug, Default)]
+pub struct Outcome {
+    pub killed: Vec<(BoardPosition, Card)>,    // Royals killed by the attack
+    pub recycled: Vec<BoardPosition>,          // Piles moved to the bottom of the deck by an ace or joker
+    pub armor_added: Option<(BoardPosition, u8)>,
+    pub royal_placed: Option<BoardPosition>,
+}
+
+// A royal that has been killed, as it is kept in the graveyard.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct KilledRoyal {
+    pub card: Card,
+    pub armor: u8,           // Armor the royal had when it was killed
+    pub move_number: usize,  // Number of the action that killed it, counting from 1 and including draws, or 0 if not known
+}
+
+// How a game ended.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GameOutcome {
+    Victory,  // All royals in the deck were killed
+    Defeat,   // The deck ran out while some royals were still alive
+    Stuck,    // There are cards left, but none of them can be placed anywhere
+}
+
+// The parts of a game that can be changed by an action. A snapshot is taken
+// before each action so that it can be undone.
+#[derive(Debug, Clone)]
+struct Snapshot {
+    drawn: Option<Card>,
+    deck: Pile,
+    board: Board,
+    shame: u8,
+    graveyard: Vec<KilledRoyal>,
+}
+
+#[derive(Debug, Clone)]
 pub struct Game {
     drawn: Option<Card>,  // Last drawn card, currently waiting to be placed
     deck: Pile,           // Remaining cards not on the board
     board: Board,
     shame: u8,
+    graveyard: Vec<KilledRoyal>,  // Royals killed so far, in the order they were killed
+    seed: u64,            // Seed used to shuffle the deck; the same seed always gives the same deal
+    deck_spec: Option<DeckSpec>,  // The cards that the deck was made of before it was dealt, unless it was made up by hand
+    royals_total: u8,     // Royals in the deck before it was dealt, all of which must be killed to win
+    rules: RuleSet,
+    history: Vec<(Action, Snapshot)>,  // Applied actions, each with the state from just before it
+    undone: Vec<Action>,  // Undone actions that can be redone, most recently undone at the back
+    stuck: Cell<Option<bool>>,  // Whether no card that is left can be placed, once worked out since the last change
 }
 
 impl Card {
-    pub fn new(value: u8, suit: Suit) -> Result<Card, String> {
-        if value == 0 && suit != Suit::Joker { return Err("Only jokers can have value 0.".to_string()); }
-        if value != 0 && suit == Suit::Joker { return Err("Jokers must have value 0.".to_string()); }
-        if value > 13 { return Err("Card value cannot be greater than 13.".to_string()); }
+    pub fn new(value: u8, suit: Suit) -> Result<Card, GameError> {
+        if (value == 0) != (suit == Suit::Joker) || value > 13 { return Err(GameError::InvalidCard { value, suit }); }
 
         Ok(Card { suit, value })
     }
@@ -58,30 +206,106 @@ impl Card {
     pub fn suit(&self) -> Suit { self.suit }
 }
 
+// Cards are written as their value and the first letter of their suit, such as
+// AS, 10H or QD, and jokers as JK.
+impl fmt::Display for Card {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let suit = match self.suit {
+            Suit::Spades => "S",
+            Suit::Hearts => "H",
+            Suit::Clubs => "C",
+            Suit::Diamonds => "D",
+            Suit::Joker => return write!(f, "JK"),
+        };
+        match self.value {
+            1 => write!(f, "A{}", suit),
+            11 => write!(f, "J{}", suit),
+            12 => write!(f, "Q{}", suit),
+            13 => write!(f, "K{}", suit),
+            value => write!(f, "{}{}", value, suit),
+        }
+    }
+}
+
+impl FromStr for Card {
+    type Err = GameError;
+
+    // Parse a card written as by Display, ignoring case.
+    fn from_str(text: &str) -> Result<Card, GameError> {
+        let invalid = || { GameError::InvalidNotation(text.to_string()) };
+        let upper = text.to_ascii_uppercase();
+        if upper == "JK" { return Ok(Card { suit: Suit::Joker, value: 0 }); }
+        if upper.len() < 2 || !upper.is_ascii() { return Err(invalid()); }
+
+        let (value, suit) = upper.split_at(upper.len() - 1);
+        let suit = match suit {
+            "S" => Suit::Spades,
+            "H" => Suit::Hearts,
+            "C" => Suit::Clubs,
+            "D" => Suit::Diamonds,
+            _ => return Err(invalid()),
+        };
+        let value = match value {
+            "A" => 1,
+            "J" => 11,
+            "Q" => 12,
+            "K" => 13,
+            _ if value.bytes().all(|byte| { byte.is_ascii_digit() }) && !value.starts_with('0') => {
+                value.parse().ok().filter(|value| { (2..=10).contains(value) }).ok_or_else(invalid)?
+            },
+            _ => return Err(invalid()),
+        };
+        Card::new(value, suit)
+    }
+}
+
+impl Requirement {
+    pub fn is_met_by(self, card: Card, royal: Card) -> bool {
+        match self {
+            Requirement::Any => true,
+            Requirement::SameColor => card.suit().is_same_color_as(royal.suit()),
+            Requirement::SameSuit => card.suit() == royal.suit(),
+        }
+    }
+}
+
+impl RoyalStatus {
+    // The damage needed to kill the royal.
+    pub fn health(&self) -> u8 {
+        self.card.value().saturating_add(self.armor)
+    }
+}
+
 impl Pile {
     pub fn new() -> Self {
         Self { cards: vec![] }
     }
 
-    pub fn new_shuffled_deck() -> Self {
-        use rand::seq::SliceRandom;
-
-        let mut cards = Vec::new();
-        for suit in vec![Suit::Spades, Suit::Hearts, Suit::Clubs, Suit::Diamonds] {   // TODO: Not sure if this is the best way to iterate over a static list of values.
-            for value in 1..(13 + 1) {
-                cards.push(Card::new(value, suit).unwrap());
-            }
-        }
-        cards.push(Card::new(0, Suit::Joker).unwrap());
-        cards.push(Card::new(0, Suit::Joker).unwrap());
+    // Create a pile from the given cards. The last card is on top.
+    pub fn from_cards(cards: Vec<Card>) -> Self {
+        Self { cards }
+    }
 
-        cards.shuffle(&mut rand::thread_rng());
+    // Shuffle a deck made to the given spec with the given seed. The deal only
+    // depends on the spec and the seed, so they can be used to replay a game.
+    pub fn new_shuffled_deck(spec: &DeckSpec, seed: u64) -> Self {
+        let mut cards = spec.cards();
+        SplitMix64::new(seed).shuffle(&mut cards);
 
         Self { cards }
     }
 
+    pub fn shuffle(&mut self, rng: &mut SplitMix64) {
+        rng.shuffle(&mut self.cards);
+    }
+
+    // All cards in the pile, from the bottom to the top.
+    pub fn cards(&self) -> &[Card] {
+        &self.cards
+    }
+
     pub fn top(&self) -> Option<Card> {
-        self.cards.last().map(|card| { *card })
+        self.cards.last().copied()
     }
 
     pub fn place_card_on_top(&mut self, card: Card) {
@@ -99,9 +323,8 @@ impl Pile {
         self.cards = pile.cards;
     }
 
-    pub fn draw(&mut self) -> Result<Card, String> {
-        if self.cards.is_empty() { return Err("Tried to draw card when there was no more cards in the deck.".to_string()); }
-        Ok(self.cards.pop().unwrap())
+    pub fn draw(&mut self) -> Result<Card, GameError> {
+        self.cards.pop().ok_or(GameError::DeckEmpty)
     }
 
     pub fn size(&self) -> usize {
@@ -113,6 +336,23 @@ impl Pile {
     }
 }
 
+// A pile is written as its cards from the bottom to the top, separated by
+// spaces, such as "3S 10H JK". An empty pile is written as nothing at all.
+impl fmt::Display for Pile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let cards: Vec<String> = self.cards.iter().map(|card| { card.to_string() }).collect();
+        write!(f, "{}", cards.join(" "))
+    }
+}
+
+impl FromStr for Pile {
+    type Err = GameError;
+
+    fn from_str(text: &str) -> Result<Pile, GameError> {
+        Ok(Pile { cards: text.split_whitespace().map(str::parse).collect::<Result<_, _>>()? })
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct BoardPosition {
     x: i8,
@@ -120,12 +360,9 @@ pub struct BoardPosition {
 }
 
 impl BoardPosition {
-    pub fn new((x, y): (i8, i8)) -> Result<BoardPosition, String> {
-        if x < -2 { return Err(format!("{:?} is an invalid coordiate: x coordinate {} is too low (must be between -2 and 2).", (x, y), x)) };
-        if x >  2 { return Err(format!("{:?} is an invalid coordiate: x coordinate {} is too high (must be between -2 and 2).", (x, y), x)) };
-        if y < -2 { return Err(format!("{:?} is an invalid coordiate: y coordinate {} is too low (must be between -2 and 2).", (x, y), y)) };
-        if y >  2 { return Err(format!("{:?} is an invalid coordiate: y coordinate {} is too high (must be between -2 and 2).", (x, y), y)) };
-        if (x == -2 || x == 2) && (y == -2 || y == 2) { return Err(format!("{:?} is an invalid coordianate: there are no cards in the corners of the board.", (x, y))); }
+    pub fn new((x, y): (i8, i8)) -> Result<BoardPosition, GameError> {
+        if !(-2..=2).contains(&x) || !(-2..=2).contains(&y) { return Err(GameError::OffBoard((x, y))); }
+        if (x == -2 || x == 2) && (y == -2 || y == 2) { return Err(GameError::Corner((x, y))); }
 
         Ok(BoardPosition { x, y })
     }
@@ -193,7 +430,7 @@ impl BoardPosition {
     // If self is the position of a royal, return the tiles that make up the
     // "cannon", that is, the tiles whose sum would be used as the damage when
     // attacking the royal at self. In other cases, return an empty vector.
-    fn cannon_towards(&self) -> Vec<BoardPosition> {
+    pub fn cannon_towards(&self) -> Vec<BoardPosition> {
         if self.x == -2 { vec![BoardPosition::new((-1, self.y)).unwrap(), BoardPosition::new((0, self.y)).unwrap()] }
         else if self.x == 2 { vec![BoardPosition::new((0, self.y)).unwrap(), BoardPosition::new((1, self.y)).unwrap()] }
         else if self.y == -2 { vec![BoardPosition::new((self.x, -1)).unwrap(), BoardPosition::new((self.x, 0)).unwrap()] }
@@ -205,28 +442,30 @@ impl BoardPosition {
     pub fn y(&self) -> i8 { self.y }
 }
 
+// Positions are described in words for the player, such as "the centre", "the
+// top left of the cannon" or "the middle of the right edge".
+impl fmt::Display for BoardPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let row = |y: i8| { ["top", "middle", "bottom"][(y + 1) as usize] };
+        let column = |x: i8| { ["left", "middle", "right"][(x + 1) as usize] };
+        match (self.x, self.y) {
+            (0, 0) => write!(f, "the centre"),
+            (0, y) if self.is_cannon() => write!(f, "the {} of the cannon", row(y)),
+            (x, 0) if self.is_cannon() => write!(f, "the {} of the cannon", column(x)),
+            (x, y) if self.is_cannon() => write!(f, "the {} {} of the cannon", row(y), column(x)),
+            (-2, y) => write!(f, "the {} of the left edge", row(y)),
+            (2, y) => write!(f, "the {} of the right edge", row(y)),
+            (x, -2) => write!(f, "the {} of the top edge", column(x)),
+            (x, _) => write!(f, "the {} of the bottom edge", column(x)),
+        }
+    }
+}
+
 impl Board {
     pub fn new_empty() -> Board {
-        // TODO: This is probably a bad way of doing this. Also, I don't really
-        // understand the code, I just copied it from StackOverflow.
-        //
         // We can't do this in the obvious way, [[Pile::new(); 5]; 5], because
         // Pile does not (and cannot) implement Copy.
-        //
-        // https://stackoverflow.com/questions/31360993/what-is-the-proper-way-to-initialize-a-fixed-length-array
-        let stacks = unsafe {
-            let mut result: [[Pile; 5]; 5] = std::mem::uninitialized();
-            for (_, element) in result.iter_mut().enumerate() {
-                let mut value: [Pile; 5] = std::mem::uninitialized();
-                for (_, element) in value.iter_mut().enumerate() {
-                    std::ptr::write(element, Pile::new());
-                }
-                std::ptr::write(element, value);
-            }
-            result
-        };
-
-        Board { stacks, armor: [[0; 5]; 5] }
+        Board { stacks: Default::default(), armor: [[0; 5]; 5] }
     }
 
     pub fn get_pile_at(&self, pos: BoardPosition) -> &Pile {
@@ -255,7 +494,8 @@ impl Board {
     // TODO: This should probably check whether it even makes sense for that
     // card to have armor.
     pub fn add_armor_at(&mut self, pos: BoardPosition, amount: u8) {
-        self.armor[(2 + pos.x()) as usize][(2 + pos.y()) as usize] += amount;
+        let armor = &mut self.armor[(2 + pos.x()) as usize][(2 + pos.y()) as usize];
+        *armor = armor.saturating_add(amount);
     }
 
     pub fn get_armor_at(&self, pos: BoardPosition) -> u8 {
@@ -270,64 +510,93 @@ impl Board {
         result
     }
 
-    pub fn resolve_attack(&mut self, royal: BoardPosition) {
-        if let Some(royal_card) = self.get_card_at(royal) {
-            let health = royal_card.value() + self.get_armor_at(royal);
+    // Work out what would happen if the royal at the given position was
+    // attacked by its cannon, or return None if there is no royal there.
+    pub fn attack_on(&self, royal: BoardPosition, rules: &RuleSet) -> Option<AttackPreview> {
+        let royal_card = self.get_card_at(royal)?;
+        let health = royal_card.value().saturating_add(self.get_armor_at(royal));
+
+        let requirement = rules.requirement_for(royal_card);
+        let mut suit_ok = true;
+        let mut is_loaded = true;
+
+        let mut damage: u8 = 0;
+        for cannon_pos in royal.cannon_towards() {
+            if let Some(card) = self.get_card_at(cannon_pos) {
+                if !requirement.is_met_by(card, royal_card) { suit_ok = false; }
+                damage = damage.saturating_add(card.value());
+            } else {
+                is_loaded = false;
+            }
+        }
 
-            let mut is_valid_attack = true;
+        let dies = damage >= health && suit_ok && is_loaded;
+        Some(AttackPreview { royal, card: royal_card, damage, health, suit_ok, dies })
+    }
 
-            let mut damage = 0;
-            for cannon_pos in royal.cannon_towards() {
-                if let Some(card) = self.get_card_at(cannon_pos) {
-                    if royal_card.value() == 12 && !card.suit().is_same_color_as(royal_card.suit()) { is_valid_attack = false; }
-                    if royal_card.value() == 13 && card.suit() != royal_card.suit() { is_valid_attack = false; }
-                    damage += card.value();
-                } else {
-                    is_valid_attack = false;
-                }
-            }
+    // Describe the royal at the given position, or return None if there is
+    // no royal there.
+    pub fn royal_status(&self, pos: BoardPosition, rules: &RuleSet) -> Option<RoyalStatus> {
+        if !pos.is_edge() { return None; }
+        let card = self.get_card_at(pos)?;
+        let damage = pos.cannon_towards().into_iter()
+            .filter_map(|cannon_pos| { self.get_card_at(cannon_pos) })
+            .fold(0u8, |damage, card| { damage.saturating_add(card.value()) });
+        let triggers = BoardPosition::all_valid().into_iter()
+            .filter(|trigger| { trigger.is_outer_cannon() && trigger.aimed_at().contains(&pos) })
+            .collect();
+        Some(RoyalStatus { position: pos, card, armor: self.get_armor_at(pos), damage, requirement: rules.requirement_for(card), triggers })
+    }
 
-            if damage >= health && is_valid_attack {
-                self.remove_pile_at(royal);
-            }
-        }
+    // Describe every royal on the board.
+    pub fn royal_statuses(&self, rules: &RuleSet) -> Vec<RoyalStatus> {
+        BoardPosition::all_valid().into_iter().filter_map(|pos| { self.royal_status(pos, rules) }).collect()
+    }
+
+    // Attack the royal at the given position with its cannon. If the royal is
+    // killed, it is removed from the board and returned.
+    pub fn resolve_attack(&mut self, royal: BoardPosition, rules: &RuleSet) -> Option<Card> {
+        let attack = self.attack_on(royal, rules)?;
+        if !attack.dies { return None; }
+        self.remove_pile_at(royal);
+        Some(attack.card)
     }
 
+    // Return the empty edge positions next to the card on the outer cannon
+    // that is most like the royal, where the rules decide what that means.
     // TODO: Error checking
-    pub fn find_valid_royal_placement_positions(&self, royal: Card) -> Vec<BoardPosition> {
+    pub fn find_valid_royal_placement_positions(&self, royal: Card, rules: &RuleSet) -> Vec<BoardPosition> {
         let mut result = vec![];
 
-        #[derive(PartialEq, Eq, PartialOrd, Ord)]
-        enum SuitSimilarity { None, Color, Suit };
+        #[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+        enum SuitSimilarity { None, Color, Suit }
 
-        let mut best_suit_similarity = SuitSimilarity::None;
-        let mut best_value = 0;
+        let mut best = None;
 
         for pos in BoardPosition::all_valid() {
             if !pos.is_outer_cannon() { continue }
 
             if self.adjacent_empty_edges(pos).is_empty() { continue }
 
+            let card = match self.get_card_at(pos) {
+                Some(card) => card,
+                None => continue,
+            };
+
             let suit_similarity = {
-                if self.get_card_at(pos).unwrap().suit() == royal.suit() { SuitSimilarity::Suit }
-                else if self.get_card_at(pos).unwrap().suit().is_same_color_as(royal.suit()) { SuitSimilarity::Color }
+                if card.suit() == royal.suit() { SuitSimilarity::Suit }
+                else if card.suit().is_same_color_as(royal.suit()) { SuitSimilarity::Color }
                 else { SuitSimilarity::None }
             };
-            let value = self.get_card_at(pos).unwrap().value();
-
-            if suit_similarity < best_suit_similarity { continue }
-            else if suit_similarity == best_suit_similarity { /* Do nothing */ }
-            else {
-                best_suit_similarity = suit_similarity;
-                result.clear();
-                best_value = value;
-            }
+            let similarity = match rules.royal_placement {
+                RoyalPlacement::SuitFirst => (suit_similarity as u8, card.value()),
+                RoyalPlacement::ValueFirst => (card.value(), suit_similarity as u8),
+            };
 
-            if value < best_value { continue }
-            else if value == best_value { /* Do nothing */ }
-            else {
+            if matches!(best, Some(best) if similarity < best) { continue }
+            if best != Some(similarity) {
+                best = Some(similarity);
                 result.clear();
-                best_value = value;
             }
 
             for adj in self.adjacent_empty_edges(pos) { result.push(adj) }
@@ -337,18 +606,105 @@ impl Board {
     }
 }
 
+impl Default for Game {
+    fn default() -> Game {
+        Game::new()
+    }
+}
+
 impl Game {
+    // Start a new game with a random seed.
     pub fn new() -> Game {
-        Game { drawn: None, deck: Pile::new_shuffled_deck(), board: Board::new_empty(), shame: 0 }
+        Game::with_seed(rand::random())
     }
 
-    pub fn set_up(&mut self) {
+    // Start a new game whose standard deck is shuffled with the given seed.
+    // Two games created with the same seed are dealt exactly the same cards.
+    pub fn with_seed(seed: u64) -> Game {
+        Game::dealt(DeckSpec::STANDARD, seed)
+    }
+
+    // Start a new game with a deck made to the given spec, shuffled with the
+    // given seed. Fails if the game cannot be played with that deck.
+    pub fn with_deck(spec: DeckSpec, seed: u64) -> Result<Game, GameError> {
+        spec.check().map_err(GameError::InvalidDeck)?;
+        Ok(Game::dealt(spec, seed))
+    }
+
+    fn dealt(spec: DeckSpec, seed: u64) -> Game {
+        Game::from_parts(Pile::new_shuffled_deck(&spec, seed), Board::new_empty(), None, 0, seed, Some(spec))
+    }
+
+    // Start a new game that is dealt from the given deck instead of a shuffled
+    // one, as usual from the top of the pile, which is written last. The board
+    // is set up like in any other game. Such a game has no seed or deck spec
+    // to replay it from, so its seed is 0 and it cannot be saved.
+    pub fn from_deck_order(deck: Pile) -> Result<Game, GameError> {
+        let mut game = Game::from_parts(deck, Board::new_empty(), None, 0, 0, None);
+        game.set_up()?;
+        Ok(game)
+    }
+
+    // Recreate a game that is already in progress, for example one that was
+    // loaded from disk. The history and the graveyard start out empty. Without
+    // a deck spec, every royal in the deck, on the board or drawn must be
+    // killed to win.
+    pub fn from_parts(deck: Pile, board: Board, drawn: Option<Card>, shame: u8, seed: u64, deck_spec: Option<DeckSpec>) -> Game {
+        let royals_total = match &deck_spec {
+            Some(spec) => spec.royals(),
+            None => {
+                let on_board = BoardPosition::all_valid().into_iter()
+                    .filter(|pos| { matches!(board.get_card_at(*pos), Some(card) if card.is_royal()) })
+                    .count();
+                (deck.royals_left() + on_board + drawn.map_or(0, |card| { card.is_royal() as usize })) as u8
+            },
+        };
+        Game {
+            drawn, deck, board, shame, graveyard: vec![], seed, deck_spec, royals_total,
+            rules: RuleSet::CLASSIC, history: vec![], undone: vec![], stuck: Cell::new(None),
+        }
+    }
+
+    // Play the game by the given rules instead of the classic ones. This
+    // should be done before any moves are made.
+    pub fn with_rules(mut self, rules: RuleSet) -> Game {
+        self.rules = rules;
+        self.stuck.set(None);
+        self
+    }
+
+    pub fn rules(&self) -> &RuleSet {
+        &self.rules
+    }
+
+    // Put back the royals that were killed before a position that is being
+    // recreated, along with how many there were to kill in all.
+    pub(crate) fn restore_graveyard(&mut self, graveyard: Vec<KilledRoyal>, royals_total: u8) {
+        self.graveyard = graveyard;
+        self.royals_total = royals_total;
+    }
+
+    // The spec that the deck was made to, or None if the game was dealt from
+    // cards given by hand.
+    pub fn deck_spec(&self) -> Option<&DeckSpec> {
+        self.deck_spec.as_ref()
+    }
+
+    // The number of royals that must be killed to win.
+    pub fn royals_total(&self) -> u8 {
+        self.royals_total
+    }
+
+    // Deal the starting cards onto the cannon. Fails if the deck runs out
+    // before every cannon tile has a card.
+    pub fn set_up(&mut self) -> Result<(), GameError> {
+        self.stuck.set(None);
         let mut royals_pile = Pile::new();
 
         for position in BoardPosition::all_valid() {
             if !position.is_outer_cannon() { continue }
             'search_card: loop {
-                let card = self.deck.draw().unwrap();
+                let card = self.deck.draw()?;
                 if card.is_royal() {
                     royals_pile.place_card_on_top(card);
                 } else {
@@ -359,32 +715,56 @@ impl Game {
         }
 
         self.deck.place_pile_on_top(royals_pile);
+        Ok(())
     }
 
     pub fn can_place_at(&self, pos: BoardPosition) -> bool {
-        // TODO: We should probably clean up this code a litte bit...
-        if self.drawn.is_none() {
-            false
+        self.check_placement(pos).is_ok()
+    }
+
+    // Check whether the drawn card may be placed at the given position, and
+    // if not, explain why.
+    fn check_placement(&self, pos: BoardPosition) -> Result<(), GameError> {
+        self.check_placement_of(self.drawn.ok_or(GameError::NothingDrawn)?, pos)
+    }
+
+    fn check_placement_of(&self, drawn: Card, pos: BoardPosition) -> Result<(), GameError> {
+        let illegal = |reason: String| { Err(GameError::IllegalPlacement { reason }) };
+        if drawn.is_royal() {
+            if self.board.find_valid_royal_placement_positions(drawn, &self.rules).contains(&pos) { Ok(()) }
+            else { illegal(format!("{} is not next to the card on the cannon that is most like {}", pos, drawn)) }
+        } else if pos.is_cannon() {
+            match self.get_card_at(pos) {
+                Some(card) if !self.rules.can_stack(drawn, card) => {
+                    illegal(format!("{} cannot be stacked on {} at {}", drawn, card, pos))
+                },
+                _ => Ok(()),
+            }
+        } else if self.get_card_at(pos).is_some() {
+            Ok(())  // Can place card to add armor
         } else {
-            let drawn = self.drawn.unwrap();
-            if drawn.is_royal() {
-                self.board.find_valid_royal_placement_positions(drawn).contains(&pos)
-            } else {
-                if pos.is_cannon() {
-                    match self.get_card_at(pos) {
-                        None => true,
-                        Some(card) => {
-                            drawn.value() == 1 || drawn.value() >= card.value()
-                        },
-                    }
-                } else {
-                    if self.get_card_at(pos).is_some() {
-                        true  // Can place card to add armor
-                    } else {
-                        false
-                    }
+            illegal(format!("there is no royal at {} to add armor to", pos))
+        }
+    }
+
+    // Return every action that the player can take right now. If a card is
+    // drawn, shaming it is always legal, so it is the only option exactly when
+    // the card cannot be placed anywhere.
+    pub fn legal_actions(&self) -> Vec<LegalAction> {
+        match self.drawn {
+            None if self.deck.size() > 0 => vec![LegalAction::Draw],
+            None => vec![],
+            Some(drawn) => {
+                let mut result = vec![];
+                for pos in BoardPosition::all_valid() {
+                    if !self.can_place_at(pos) { continue }
+                    result.push(if drawn.is_royal() { LegalAction::Royal(pos) }
+                                else if pos.is_cannon() { LegalAction::Stack(pos) }
+                                else { LegalAction::Armor(pos) });
                 }
-            }
+                result.push(LegalAction::Shame);
+                result
+            },
         }
     }
 
@@ -392,39 +772,143 @@ impl Game {
         self.board.get_card_at(pos)
     }
 
-    pub fn place_card_at(&mut self, pos: BoardPosition) -> Option<()> {
-        if !self.can_place_at(pos) { return None; }
+    // Work out which royals would be attacked if a card was placed at the
+    // given position, and how each attack would turn out, without changing the
+    // game.
+    pub fn preview(&self, pos: BoardPosition) -> Vec<AttackPreview> {
+        pos.aimed_at().into_iter().filter_map(|royal| { self.board.attack_on(royal, &self.rules) }).collect()
+    }
 
-        if self.drawn.is_none() {
-            panic!();
-        } else {
-            let card = self.get_card_at(pos);
-            if card.is_some() && card.unwrap().is_royal() {
-                self.add_armor_at(pos).unwrap();
-            } else {
-                if self.drawn.unwrap().value() == 1 || self.drawn.unwrap().value() == 0 {
-                    self.move_pile_to_bottom_of_deck_at(pos);
-                }
+    // Describe every royal on the board.
+    pub fn royal_statuses(&self) -> Vec<RoyalStatus> {
+        self.board.royal_statuses(&self.rules)
+    }
 
-                for attacked in pos.aimed_at() {
-                    self.board.resolve_attack(attacked);
-                }
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
 
-                self.board.place_card_at(pos, self.drawn.unwrap());
-                self.drawn = None;
-            }
-            Some(())
+    // The cards that have not been drawn yet. The next card to be drawn is on
+    // top.
+    pub fn deck(&self) -> &Pile {
+        &self.deck
+    }
+
+    // Perform an action on behalf of the player and report what happened. The
+    // action is recorded in the history so that it can be undone.
+    pub fn apply(&mut self, action: Action) -> Result<Outcome, GameError> {
+        let snapshot = self.snapshot();
+        let outcome = self.perform(action)?;
+        self.history.push((action, snapshot));
+        self.undone.clear();
+        Ok(outcome)
+    }
+
+    // The actions that led to the current state, oldest first. Undone actions
+    // are not included.
+    pub fn history(&self) -> Vec<Action> {
+        self.history.iter().map(|(action, _)| { *action }).collect()
+    }
+
+    // Take back the most recent action, returning it, or None if there is
+    // nothing to undo.
+    pub fn undo(&mut self) -> Option<Action> {
+        let (action, snapshot) = self.history.pop()?;
+        self.restore(snapshot);
+        self.undone.push(action);
+        Some(action)
+    }
+
+    // Apply the most recently undone action again, returning it, or None if
+    // there is nothing to redo or it cannot be applied, in which case it stays
+    // to be redone.
+    pub fn redo(&mut self) -> Option<Action> {
+        let action = *self.undone.last()?;
+        let snapshot = self.snapshot();
+        self.perform(action).ok()?;
+        self.undone.pop();
+        self.history.push((action, snapshot));
+        Some(action)
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            drawn: self.drawn,
+            deck: self.deck.clone(),
+            board: self.board.clone(),
+            shame: self.shame,
+            graveyard: self.graveyard.clone(),
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.stuck.set(None);
+        self.drawn = snapshot.drawn;
+        self.deck = snapshot.deck;
+        self.board = snapshot.board;
+        self.shame = snapshot.shame;
+        self.graveyard = snapshot.graveyard;
+    }
+
+    // Forget the history, so that the game can be cloned cheaply. Used by
+    // searches that try many moves and undo them by throwing the copy away.
+    pub(crate) fn forget_history(&mut self) {
+        self.history.clear();
+        self.undone.clear();
+    }
+
+    // Shuffle the cards that have not been drawn yet, to try out one of the
+    // ways that they could be ordered.
+    pub(crate) fn shuffle_deck(&mut self, rng: &mut SplitMix64) {
+        self.deck.shuffle(rng);
+    }
+
+    // Perform an action without recording it in the history.
+    pub(crate) fn perform(&mut self, action: Action) -> Result<Outcome, GameError> {
+        self.stuck.set(None);
+        match action {
+            Action::Draw => self.draw(),
+            Action::Place(pos) => self.place_card_at(pos),
+            Action::Shame => self.add_to_shame_pile(),
         }
     }
 
-    pub fn add_armor_at(&mut self, pos: BoardPosition) -> Result<(), String> {
-        if self.drawn.is_none() {
-            Err("Cannot add armor because no card is drawn.".to_string())
+    fn place_card_at(&mut self, pos: BoardPosition) -> Result<Outcome, GameError> {
+        self.check_placement(pos)?;
+        let drawn = self.drawn.ok_or(GameError::NothingDrawn)?;
+
+        let mut outcome = Outcome::default();
+
+        let card = self.get_card_at(pos);
+        if card.is_some() && card.unwrap().is_royal() {
+            outcome.armor_added = Some((pos, drawn.value()));
+            self.add_armor_at(pos)?;
         } else {
-            self.board.add_armor_at(pos, self.drawn.unwrap().value());
+            if self.rules.stacking_of(drawn) != Stacking::ByValue {
+                if self.board.get_pile_at(pos).size() > 0 { outcome.recycled.push(pos); }
+                self.move_pile_to_bottom_of_deck_at(pos);
+            }
+
+            for attacked in pos.aimed_at() {
+                let armor = self.board.get_armor_at(attacked);
+                if let Some(royal) = self.board.resolve_attack(attacked, &self.rules) {
+                    outcome.killed.push((attacked, royal));
+                    self.graveyard.push(KilledRoyal { card: royal, armor, move_number: self.history.len() + 1 });
+                }
+            }
+
+            if drawn.is_royal() { outcome.royal_placed = Some(pos); }
+
+            self.board.place_card_at(pos, drawn);
             self.drawn = None;
-            Ok(())
         }
+        Ok(outcome)
+    }
+
+    fn add_armor_at(&mut self, pos: BoardPosition) -> Result<(), GameError> {
+        let drawn = self.drawn.take().ok_or(GameError::NothingDrawn)?;
+        self.board.add_armor_at(pos, drawn.value());
+        Ok(())
     }
 
     pub fn get_armor_at(&self, pos: BoardPosition) -> u8 {
@@ -435,11 +919,10 @@ impl Game {
         self.deck.place_pile_on_bottom(self.board.take_pile_at(pos));
     }
 
-    pub fn draw(&mut self) -> Result<(), String> {
-        if self.drawn.is_some() { return Err("Cannot draw card while one is already drawn.".to_string()) }
-        self.deck.draw().map(|card| {
-            self.drawn = Some(card);
-        })
+    fn draw(&mut self) -> Result<Outcome, GameError> {
+        if self.drawn.is_some() { return Err(GameError::AlreadyDrawn) }
+        self.drawn = Some(self.deck.draw()?);
+        Ok(Outcome::default())
     }
 
     pub fn drawn(&self) -> Option<Card> {
@@ -450,18 +933,437 @@ impl Game {
         self.deck.size()
     }
 
-    pub fn add_to_shame_pile(&mut self) {
-        self.drawn = None;
-        self.shame += 1;
+    fn add_to_shame_pile(&mut self) -> Result<Outcome, GameError> {
+        self.drawn.take().ok_or(GameError::NothingDrawn)?;
+        self.shame = self.shame.saturating_add(1);
+        Ok(Outcome::default())
     }
 
     pub fn get_shame(&self) -> u8 {
         self.shame
     }
 
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // The royals that have been killed, in the order they were killed.
+    pub fn graveyard(&self) -> &[KilledRoyal] {
+        &self.graveyard
+    }
+
+    pub fn royals_killed(&self) -> u8 {
+        self.graveyard.len() as u8
+    }
+
+    // Return how the game ended, or None if it is still in progress.
+    pub fn outcome(&self) -> Option<GameOutcome> {
+        if self.royals_killed() >= self.royals_total() {
+            Some(GameOutcome::Victory)
+        } else if self.drawn.is_none() && self.deck.size() == 0 {
+            Some(GameOutcome::Defeat)
+        } else if self.is_stuck() {
+            Some(GameOutcome::Stuck)
+        } else {
+            None
+        }
+    }
+
+    pub fn score(&self) -> Score {
+        Score::for_game(self)
+    }
+
     pub fn is_game_over(&self) -> bool {
-        self.deck.royals_left() == 0 ||
-            self.deck.size() == 0  // No possible actions (TODO: Handle this case in scoring)
+        self.outcome().is_some()
+    }
+
+    // Return true if none of the cards that are left, including the drawn
+    // card, can be placed anywhere on the board. The board can then never
+    // change again, so all that remains is to shame the rest of the deck.
+    // Checking every card against every tile takes a while, so the answer is
+    // kept until the game changes.
+    fn is_stuck(&self) -> bool {
+        if let Some(stuck) = self.stuck.get() { return stuck; }
+        let remaining = self.deck.cards().iter().chain(self.drawn.iter());
+        let stuck = !remaining.into_iter().any(|card| {
+            BoardPosition::all_valid().into_iter().any(|pos| { self.check_placement_of(*card, pos).is_ok() })
+        });
+        self.stuck.set(Some(stuck));
+        stuck
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::write_position;
+
+    fn pos(x: i8, y: i8) -> BoardPosition {
+        BoardPosition::new((x, y)).unwrap()
+    }
+
+    fn card(text: &str) -> Card {
+        text.parse().unwrap()
+    }
+
+    // A game with the given piles on the board, each given by its position and
+    // its cards from the bottom to the top.
+    fn game_with(piles: &[(i8, i8, &str)], drawn: Option<&str>, deck: &str) -> Game {
+        let mut board = Board::new_empty();
+        for (x, y, cards) in piles {
+            for card in cards.parse::<Pile>().unwrap().cards() {
+                board.place_card_at(pos(*x, *y), *card);
+            }
+        }
+        Game::from_parts(deck.parse().unwrap(), board, drawn.map(card), 0, 0, Some(DeckSpec::STANDARD))
+    }
+
+    #[test]
+    fn draw_takes_the_top_card() {
+        let mut game = game_with(&[], None, "2S 3S");
+        let outcome = game.apply(Action::Draw).unwrap();
+        assert!(outcome.killed.is_empty() && outcome.recycled.is_empty());
+        assert_eq!(game.drawn(), Some(card("3S")));
+        assert_eq!(game.apply(Action::Draw).unwrap_err(), GameError::AlreadyDrawn);
+    }
+
+    #[test]
+    fn placing_a_card_reports_the_kill() {
+        // The card at (-1,0) fires the cannon at the Jack at (2,0), which is
+        // loaded with 5 + 6
+        let mut game = game_with(&[(0, 0, "5H"), (1, 0, "6C"), (2, 0, "JS")], Some("4D"), "");
+        let outcome = game.apply(Action::Place(pos(-1, 0))).unwrap();
+        assert_eq!(outcome.killed, vec![(pos(2, 0), card("JS"))]);
+        assert_eq!(game.get_card_at(pos(2, 0)), None);
+        assert_eq!(game.graveyard()[0].card, card("JS"));
+    }
+
+    #[test]
+    fn placing_an_ace_reports_the_recycled_pile() {
+        let mut game = game_with(&[(0, 1, "4S 9H")], Some("AC"), "2D");
+        let outcome = game.apply(Action::Place(pos(0, 1))).unwrap();
+        assert_eq!(outcome.recycled, vec![pos(0, 1)]);
+        assert_eq!(game.board().get_pile_at(pos(0, 1)).cards(), &[card("AC")]);
+        assert_eq!(game.deck().to_string(), "4S 9H 2D");
+    }
+
+    #[test]
+    fn placing_an_ace_on_an_empty_tile_recycles_nothing() {
+        let mut game = game_with(&[], Some("AC"), "2D");
+        let outcome = game.apply(Action::Place(pos(0, 1))).unwrap();
+        assert!(outcome.recycled.is_empty());
+        assert_eq!(game.deck().to_string(), "2D");
+    }
+
+    #[test]
+    fn placing_on_a_royal_reports_the_armor() {
+        let mut game = game_with(&[(1, 0, "6C"), (2, 0, "QS")], Some("3H"), "");
+        let outcome = game.apply(Action::Place(pos(2, 0))).unwrap();
+        assert_eq!(outcome.armor_added, Some((pos(2, 0), 3)));
+        assert_eq!(game.get_armor_at(pos(2, 0)), 3);
+    }
+
+    #[test]
+    fn placing_a_royal_reports_where_it_went() {
+        // The Queen goes next to the Heart, not the Club
+        let mut game = game_with(&[(1, 0, "6H"), (-1, 0, "9C")], Some("QH"), "");
+        assert!(game.apply(Action::Place(pos(-2, 0))).is_err());
+        let outcome = game.apply(Action::Place(pos(2, 0))).unwrap();
+        assert_eq!(outcome.royal_placed, Some(pos(2, 0)));
+    }
+
+    #[test]
+    fn illegal_placement_changes_nothing() {
+        let mut game = game_with(&[(0, 0, "9H")], Some("2C"), "");
+        assert!(matches!(game.apply(Action::Place(pos(0, 0))), Err(GameError::IllegalPlacement { .. })));
+        assert_eq!(game.drawn(), Some(card("2C")));
+        assert!(game.history().is_empty());
+    }
+
+    #[test]
+    fn illegal_placement_is_explained_in_words() {
+        let game = game_with(&[(0, 0, "9H"), (1, -1, "6S")], Some("2C"), "");
+        let reason = |x, y| { game.check_placement(pos(x, y)).unwrap_err().to_string() };
+        assert_eq!(reason(0, 0), "Cannot place card: 2C cannot be stacked on 9H at the centre.");
+        assert_eq!(reason(-2, 1), "Cannot place card: there is no royal at the bottom of the left edge to add armor to.");
+
+        let game = game_with(&[(1, -1, "6S")], Some("KS"), "");
+        assert_eq!(game.check_placement(pos(-1, -2)).unwrap_err().to_string(),
+                   "Cannot place card: the left of the top edge is not next to the card on the cannon that is most like KS.");
+        assert_eq!(pos(1, -1).to_string(), "the top right of the cannon");
+    }
+
+    #[test]
+    fn number_cards_can_stack_armor_or_be_shamed() {
+        use LegalAction::*;
+        let game = game_with(&[(0, 0, "9H"), (1, 0, "6C"), (2, 0, "QS")], Some("7D"), "");
+        assert_eq!(game.legal_actions(), vec![
+            Stack(pos(-1, -1)), Stack(pos(-1, 0)), Stack(pos(-1, 1)),
+            Stack(pos(0, -1)), Stack(pos(0, 1)),  // Not on the 9H in the centre
+            Stack(pos(1, -1)), Stack(pos(1, 0)), Stack(pos(1, 1)),
+            Armor(pos(2, 0)),
+            Shame,
+        ]);
+    }
+
+    #[test]
+    fn royals_can_only_go_on_the_edge_or_be_shamed() {
+        let game = game_with(&[(1, 0, "6H"), (-1, 0, "9C")], Some("QH"), "");
+        assert_eq!(game.legal_actions(), vec![LegalAction::Royal(pos(2, 0)), LegalAction::Shame]);
+    }
+
+    #[test]
+    fn shame_is_the_only_option_when_nothing_fits() {
+        let cannon: Vec<(i8, i8, &str)> = BoardPosition::all_valid().into_iter()
+            .filter(|pos| { pos.is_cannon() })
+            .map(|pos| { (pos.x(), pos.y(), "10S") })
+            .collect();
+        let game = game_with(&cannon, Some("2C"), "");
+        assert_eq!(game.legal_actions(), vec![LegalAction::Shame]);
+    }
+
+    #[test]
+    fn drawing_is_the_only_option_without_a_drawn_card() {
+        assert_eq!(game_with(&[], None, "2S").legal_actions(), vec![LegalAction::Draw]);
+        assert_eq!(game_with(&[], None, "").legal_actions(), vec![]);
+    }
+
+    #[test]
+    fn preview_reports_the_damage_to_each_royal_in_line() {
+        let game = game_with(&[(0, 0, "5H"), (1, 0, "6C"), (2, 0, "JS")], Some("4D"), "");
+        assert_eq!(game.preview(pos(-1, 0)), vec![
+            AttackPreview { royal: pos(2, 0), card: card("JS"), damage: 11, health: 11, suit_ok: true, dies: true },
+        ]);
+        assert!(game.preview(pos(0, 0)).is_empty());
+        assert!(game.preview(pos(1, 0)).is_empty());  // Fires at the left edge, where there is no royal
+    }
+
+    #[test]
+    fn preview_counts_armor_towards_health() {
+        let mut game = game_with(&[(0, 0, "5H"), (1, 0, "6H"), (2, 0, "QH")], Some("3S"), "9D");
+        game.apply(Action::Place(pos(2, 0))).unwrap();
+        game.apply(Action::Draw).unwrap();
+        let attack = game.preview(pos(-1, 0))[0];
+        assert_eq!((attack.damage, attack.health, attack.suit_ok, attack.dies), (11, 15, true, false));
+    }
+
+    #[test]
+    fn preview_applies_the_queen_and_king_requirements() {
+        // Clubs are the wrong colour for the Queen of Hearts
+        let game = game_with(&[(0, 0, "5H"), (1, 0, "6C"), (2, 0, "QH")], Some("4D"), "");
+        let attack = game.preview(pos(-1, 0))[0];
+        assert_eq!((attack.damage, attack.suit_ok, attack.dies), (11, false, false));
+
+        // Diamonds are the right colour but the wrong suit for the King of Hearts
+        let game = game_with(&[(0, 0, "5D"), (1, 0, "9H"), (2, 0, "KH")], Some("4D"), "");
+        assert!(!game.preview(pos(-1, 0))[0].suit_ok);
+        let game = game_with(&[(0, 0, "5H"), (1, 0, "9H"), (2, 0, "KH")], Some("4D"), "");
+        assert!(game.preview(pos(-1, 0))[0].dies);
+    }
+
+    #[test]
+    fn preview_needs_both_cannon_tiles() {
+        let game = game_with(&[(1, 0, "10C"), (2, 0, "JS")], Some("4D"), "");
+        let attack = game.preview(pos(-1, 0))[0];
+        assert_eq!((attack.damage, attack.suit_ok, attack.dies), (10, true, false));
+    }
+
+    #[test]
+    fn preview_does_not_change_the_game() {
+        let game = game_with(&[(0, 0, "5H"), (1, 0, "6C"), (2, 0, "JS")], Some("4D"), "7S");
+        let before = write_position(&game);
+        for pos in BoardPosition::all_valid() {
+            game.preview(pos);
+        }
+        assert_eq!(write_position(&game), before);
+        assert_eq!(game.drawn(), Some(card("4D")));
+    }
+
+    #[test]
+    fn royal_status_describes_the_royal_and_its_cannon() {
+        let mut game = game_with(&[(-1, -1, "4C"), (0, 0, "5H"), (1, 0, "6C"), (2, 0, "KH"), (2, -1, "JD")], Some("3S"), "");
+        game.apply(Action::Place(pos(2, 0))).unwrap();
+
+        let status = game.board().royal_status(pos(2, 0), game.rules()).unwrap();
+        assert_eq!(status, RoyalStatus {
+            position: pos(2, 0), card: card("KH"), armor: 3, damage: 11,
+            requirement: Requirement::SameSuit, triggers: vec![pos(-1, 0)],
+        });
+        assert_eq!(status.health(), 16);
+
+        // Royals beside a corner of the cannon are fired at from the far corner
+        let status = game.board().royal_status(pos(2, -1), game.rules()).unwrap();
+        assert_eq!((status.damage, status.triggers), (0, vec![pos(-1, -1)]));
+
+        assert_eq!(game.board().royal_status(pos(0, 0), game.rules()), None);
+        assert_eq!(game.board().royal_status(pos(-2, 0), game.rules()), None);
+        assert_eq!(game.royal_statuses().len(), 2);
+    }
+
+    #[test]
+    fn royal_status_requirements_follow_the_rules() {
+        let board = game_with(&[(2, 0, "JS"), (-2, 0, "QS"), (0, 2, "KS")], None, "").board().clone();
+        let requirements = |rules: &RuleSet| -> Vec<Requirement> {
+            [pos(2, 0), pos(-2, 0), pos(0, 2)].iter().map(|pos| { board.royal_status(*pos, rules).unwrap().requirement }).collect()
+        };
+        assert_eq!(requirements(&RuleSet::CLASSIC), vec![Requirement::Any, Requirement::SameColor, Requirement::SameSuit]);
+        assert_eq!(requirements(&RuleSet::STRICT), vec![Requirement::SameColor, Requirement::SameColor, Requirement::SameSuit]);
+        assert_eq!(requirements(&RuleSet::RELAXED), vec![Requirement::Any, Requirement::Any, Requirement::Any]);
+    }
+
+    #[test]
+    fn undo_restores_the_position_before_the_action() {
+        let mut game = game_with(&[(0, 0, "5H"), (1, 0, "6C"), (2, 0, "JS")], None, "4D 9S");
+        let start = write_position(&game);
+        game.apply(Action::Draw).unwrap();
+        let drawn = write_position(&game);
+        game.apply(Action::Place(pos(-1, 0))).unwrap();
+        assert_eq!(game.royals_killed(), 1);
+
+        assert_eq!(game.undo(), Some(Action::Place(pos(-1, 0))));
+        assert_eq!(write_position(&game), drawn);
+        assert_eq!(game.royals_killed(), 0);
+        assert_eq!(game.undo(), Some(Action::Draw));
+        assert_eq!(write_position(&game), start);
+        assert_eq!(game.undo(), None);
+        assert!(game.history().is_empty());
+    }
+
+    #[test]
+    fn redo_makes_the_undone_actions_again() {
+        let mut game = game_with(&[(0, 0, "5H"), (1, 0, "6C"), (2, 0, "JS")], None, "4D 9S");
+        game.apply(Action::Draw).unwrap();
+        game.apply(Action::Place(pos(-1, 0))).unwrap();
+        let end = write_position(&game);
+
+        game.undo();
+        game.undo();
+        assert_eq!(game.redo(), Some(Action::Draw));
+        assert_eq!(game.redo(), Some(Action::Place(pos(-1, 0))));
+        assert_eq!(write_position(&game), end);
+        assert_eq!(game.history(), vec![Action::Draw, Action::Place(pos(-1, 0))]);
+        assert_eq!(game.redo(), None);
+    }
+
+    #[test]
+    fn a_new_action_cannot_be_followed_by_redo() {
+        let mut game = game_with(&[], Some("2C"), "3C");
+        game.apply(Action::Shame).unwrap();
+        game.undo();
+        game.apply(Action::Place(pos(0, 0))).unwrap();
+        assert_eq!(game.redo(), None);
+        assert_eq!(game.get_shame(), 0);
+    }
+
+    #[test]
+    fn failed_redo_keeps_the_action() {
+        let mut game = game_with(&[], Some("2C"), "3C");
+        game.apply(Action::Shame).unwrap();
+        game.undo();
+        game.perform(Action::Shame).unwrap();
+        assert_eq!(game.redo(), None);
+
+        game.perform(Action::Draw).unwrap();
+        assert_eq!(game.redo(), Some(Action::Shame));
+    }
+
+    #[test]
+    fn shaming_adds_to_the_shame_pile() {
+        let mut game = game_with(&[], Some("2C"), "");
+        game.apply(Action::Shame).unwrap();
+        assert_eq!((game.get_shame(), game.drawn()), (1, None));
+        assert_eq!(game.apply(Action::Shame).unwrap_err(), GameError::NothingDrawn);
+    }
+
+    // The cannon is dealt 2C to 8C from the top left, going down each column
+    // in turn, and 9S in the bottom right; the cards before them are left in
+    // the deck.
+    fn dealt_game(deck: &str) -> Game {
+        Game::from_deck_order(format!("{} 9S 8C 7C 6C 5C 4C 3C 2C", deck).parse().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn games_dealt_by_hand_count_their_own_royals() {
+        let game = dealt_game("QD 10H JS");
+        assert_eq!(game.deck_spec(), None);
+        assert_eq!(game.royals_total(), 2);
+
+        let game = Game::with_deck(DeckSpec::MARATHON, 1).unwrap();
+        assert_eq!(game.deck_spec(), Some(&DeckSpec::MARATHON));
+        assert_eq!(game.royals_total(), 24);
+    }
+
+    #[test]
+    fn killing_every_royal_is_a_victory() {
+        let mut game = dealt_game("10H JS");
+        assert_eq!(game.royals_total(), 1);
+        game.apply(Action::Draw).unwrap();
+        game.apply(Action::Place(pos(2, 1))).unwrap();  // Next to the 9S
+        game.apply(Action::Draw).unwrap();
+        assert_eq!(game.outcome(), None);
+
+        // Fires 6C and 9S at the Jack
+        game.apply(Action::Place(pos(-1, 1))).unwrap();
+        assert_eq!(game.outcome(), Some(GameOutcome::Victory));
+        assert!(game.is_game_over());
+    }
+
+    #[test]
+    fn running_out_of_cards_is_a_defeat() {
+        let mut game = dealt_game("2H JS");
+        game.apply(Action::Draw).unwrap();
+        game.apply(Action::Place(pos(2, 1))).unwrap();
+        game.apply(Action::Draw).unwrap();
+        game.apply(Action::Shame).unwrap();
+        assert_eq!(game.outcome(), Some(GameOutcome::Defeat));
+    }
+
+    #[test]
+    fn no_card_fitting_anywhere_is_stuck() {
+        // Thirteen royals, but only twelve tiles on the edge to put them on
+        let mut game = dealt_game("KS JS QS KS JH QH KH JC QC KC JD QD KD");
+        for _ in 0..12 {
+            assert_eq!(game.outcome(), None);
+            game.apply(Action::Draw).unwrap();
+            let action = game.legal_actions()[0].action();
+            game.apply(action).unwrap();
+        }
+        assert_eq!(game.outcome(), Some(GameOutcome::Stuck));
+
+        // Taking back the last royal frees its tile again
+        game.undo();
+        assert_eq!(game.outcome(), None);
+    }
+
+    #[test]
+    fn seed_gives_a_fixed_deal() {
+        // Every saved game and replay depends on these cards, so they must
+        // never change
+        let deck = Pile::new_shuffled_deck(&DeckSpec::STANDARD, 1234);
+        let top: Vec<String> = deck.cards().iter().rev().take(5).map(|card| { card.to_string() }).collect();
+        assert_eq!(top, ["6C", "8D", "2H", "3H", "AD"]);
+
+        let mut game = Game::with_seed(1234);
+        game.set_up().unwrap();
+        assert_eq!(game.get_card_at(pos(-1, -1)), Some("6C".parse().unwrap()));
+        assert_eq!(game.get_card_at(pos(1, 0)), Some("JK".parse().unwrap()));
+        assert_eq!(game.get_card_at(pos(1, 1)), Some("9D".parse().unwrap()));
+        assert_eq!(game.get_card_at(pos(0, 0)), None);
+        assert_eq!(game.cards_left(), 54 - 8);
+    }
+
+    #[test]
+    fn deck_must_be_playable() {
+        let spec = DeckSpec { decks: 200, ..DeckSpec::STANDARD };
+        assert!(matches!(Game::with_deck(spec, 1), Err(GameError::InvalidDeck(_))));
+        assert_eq!(Game::with_deck(DeckSpec::MARATHON, 1).unwrap().royals_total(), 24);
+    }
+
+    #[test]
+    fn same_seed_gives_same_deal() {
+        let deal = |seed| { Pile::new_shuffled_deck(&DeckSpec::STANDARD, seed).to_string() };
+        assert_eq!(deal(99), deal(99));
+        assert_ne!(deal(99), deal(100));
+    }
+}
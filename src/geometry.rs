@@ -33,3 +33,19 @@ pub fn align_text<'a>(font: &Font<'a, 'static>, text: &'a str, parent: Rect, hor
     (x, y)
 }
 
+
+// Split the text into lines that each fit within the given width, breaking
+// between words.
+pub fn wrap_text(font: &Font, text: &str, width: u32) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    for word in text.split_whitespace() {
+        match lines.last_mut() {
+            Some(line) if font.size_of(&format!("{} {}", line, word)).map(|(w, _)| w <= width).unwrap_or(false) => {
+                line.push(' ');
+                line.push_str(word);
+            },
+            _ => lines.push(word.to_string()),
+        }
+    }
+    lines
+}
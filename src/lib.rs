@@ -0,0 +1,17 @@
+// The rules of the game and everything that can be done without a window,
+// shared by the game itself and the tools in src/bin.
+
+pub mod bot;
+pub mod daily;
+pub mod deck;
+pub mod game;
+pub mod notation;
+pub mod paths;
+pub mod puzzle;
+pub mod random;
+pub mod replay;
+pub mod rules;
+pub mod save;
+pub mod score;
+pub mod solver;
+pub mod stats;
@@ -1,17 +1,28 @@
-mod game;
 mod geometry;
 
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
 use sdl2::pixels::Color;
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::keyboard::{Keycode, Mod};
 use sdl2::rect::Rect;
 use sdl2::render::{TextureCreator, WindowCanvas};
 use sdl2::video::WindowContext;
 use sdl2::mouse::{MouseButton, Cursor, SystemCursor};
 use sdl2::ttf::{Font};
 
-use game::{BoardPosition, Game, Card, Suit};
-use geometry::{align_text, HorizontalAlignment as AlignH, VerticalAlignment as AlignV};
+use squaretillery::game::{Action, BoardPosition, Game, GameOutcome, Card, LegalAction, RoyalStatus, Suit};
+use squaretillery::{bot, daily, notation, paths, puzzle, replay, save, solver, stats};
+use squaretillery::bot::Strategy;
+use squaretillery::puzzle::{GoalStatus, Puzzle};
+use squaretillery::replay::Replay;
+use squaretillery::deck::DeckSpec;
+use squaretillery::rules::RuleSet;
+use squaretillery::stats::Stats;
+use geometry::{align_text, wrap_text, HorizontalAlignment as AlignH, VerticalAlignment as AlignV};
 
 // TODO: We do integer conversions (mostly between i32 and u32) in a lot of
 // places. Is there a way to avoid that? Should we switch to safe conversions?
@@ -26,6 +37,10 @@ const UI_SPACE: i32 = 8;
 const DRAW_PILE_POSITION: (i32, i32) = ((CARD_SPACE + CARD_WIDTH as i32) * 5 + CARD_SPACE, CARD_SPACE);
 const WINDOW_WIDTH: u32 = (CARD_WIDTH + CARD_SPACE as u32) * 6 + CARD_SPACE as u32;
 const WINDOW_HEIGHT: u32 = (CARD_WIDTH + CARD_SPACE as u32) * 5 + CARD_SPACE as u32;
+const GRAVEYARD_CARD_WIDTH: u32 = 42;
+const GRAVEYARD_SPACE: i32 = 12;
+const GRAVEYARD_COLUMNS: usize = 3;
+const GRAVEYARD_POSITION: (i32, i32) = (DRAW_PILE_POSITION.0, WINDOW_HEIGHT as i32 - CARD_SPACE - (GRAVEYARD_CARD_WIDTH as i32 + GRAVEYARD_SPACE) * 4 + GRAVEYARD_SPACE);
 
 fn translate_screen_to_board((x, y): (i32, i32)) -> Option<BoardPosition> {
     let board_x = x / (CARD_SPACE + CARD_WIDTH as i32) - 2;
@@ -53,6 +68,7 @@ fn draw_text<'a>(context: &mut DrawContext, font: &'a Font<'a, 'static>, text: &
     context.canvas.copy(&text_texture, None, Rect::new(x, y, w, h)).unwrap();
 }
 
+#[allow(clippy::too_many_arguments)]
 fn draw_text_align<'a>(context: &mut DrawContext, font: &'a Font<'a, 'static>, text: &'a str, color: Color,
                        parent: Rect, horizontal_alignment: AlignH, vertical_alignment: AlignV, horizontal_margin: i32, vertical_margin: i32) {
     let pos = align_text(font, text, parent, horizontal_alignment, vertical_alignment, horizontal_margin, vertical_margin);
@@ -161,21 +177,78 @@ fn draw_armor(context: &mut DrawContext, armor: u8, (x, y): (i32, i32)) {
     }
 }
 
-fn draw_card_on_board(context: &mut DrawContext, game: &Game, pos: BoardPosition, draw_placability: bool) {
+// Show how much damage the cannon aimed at a royal would deal out of the health
+// of the royal, or that it would be killed if `dies` is set.
+fn draw_royal_health(context: &mut DrawContext, status: &RoyalStatus, dies: bool) {
+    let (x, y) = translate_board_to_screen(status.position);
+    let rect = Rect::new(x, y, CARD_WIDTH, CARD_WIDTH);
+    let text = if dies { "KILL".to_string() } else { format!("{}/{}", status.damage, status.health()) };
+    draw_text_align(context, context.ui_font, &text, Color::RGB(0xFF, 0xFF, 0xFF),
+                    rect, AlignH::Right, AlignV::Bottom, CARD_TEXT_MARGIN, CARD_TEXT_MARGIN);
+}
+
+// Draw the killed royals in a grid below the draw pile, with an empty slot for
+// each royal that is still alive. Decks with more than twelve royals get
+// slots of half the size, which leaves no room to show their values.
+fn draw_graveyard(context: &mut DrawContext, game: &Game) {
+    let total = game.royals_total() as usize;
+    let scale = if total > 12 { 2 } else { 1 };
+    let (width, space, columns) = (GRAVEYARD_CARD_WIDTH / scale, GRAVEYARD_SPACE / scale as i32, GRAVEYARD_COLUMNS * scale as usize);
+    for i in 0..total {
+        let x = GRAVEYARD_POSITION.0 + (width as i32 + space) * (i % columns) as i32;
+        let y = GRAVEYARD_POSITION.1 + (width as i32 + space) * (i / columns) as i32;
+        let rect = Rect::new(x, y, width, width);
+        match game.graveyard().get(i) {
+            Some(killed) => {
+                context.canvas.set_draw_color(color_for_suit(killed.card.suit(), true));
+                context.canvas.fill_rect(rect).unwrap();
+                if scale > 1 { continue }
+                draw_text_align(context, context.ui_font, &format!("{}", killed.card.value()), Color::RGB(0xFF, 0xFF, 0xFF),
+                                rect, AlignH::Center, AlignV::Middle, 0, 0);
+            },
+            None => {
+                context.canvas.set_draw_color(Color::RGB(0xE2, 0xDB, 0xD8));
+                context.canvas.fill_rect(rect).unwrap();
+            },
+        }
+    }
+}
+
+// Draw the player's statistics in the middle of the window, in place of the
+// game.
+fn draw_stats(context: &mut DrawContext, stats: &Stats) {
+    let lines = stats.lines();
+    let line_height = UI_FONT_HEIGHT as i32 + UI_SPACE;
+    let top = (WINDOW_HEIGHT as i32 - CARD_FONT_HEIGHT as i32 - UI_SPACE - line_height * lines.len() as i32) / 2;
+    let window = Rect::new(0, 0, WINDOW_WIDTH, WINDOW_HEIGHT);
+    draw_text_align(context, context.card_font, "STATISTICS", Color::RGB(0x62, 0x5B, 0x58),
+                    window, AlignH::Center, AlignV::Top, 0, top);
+    for (i, line) in lines.iter().enumerate() {
+        draw_text_align(context, context.ui_font, line, Color::RGB(0x82, 0x7B, 0x78),
+                        window, AlignH::Center, AlignV::Top, 0, top + CARD_FONT_HEIGHT as i32 + UI_SPACE + line_height * i as i32);
+    }
+}
+
+// Draw the card at the given position. If `is_active` is given, the card is
+// drawn as active or inactive, for example to show where the dragged card
+// can be placed.
+fn draw_card_on_board(context: &mut DrawContext, game: &Game, pos: BoardPosition, is_active: Option<bool>) {
     let card = game.get_card_at(pos);
     let (x, y) = translate_board_to_screen(pos);
-    if draw_placability {
-        if game.can_place_at(pos) {
-            draw_active_card(context, card, (x, y));
-        } else {
-            draw_inactive_card(context, card, (x, y));
-        }
-    } else {
-        draw_card(context, card, (x, y));
+    match is_active {
+        Some(true) => draw_active_card(context, card, (x, y)),
+        Some(false) => draw_inactive_card(context, card, (x, y)),
+        None => draw_card(context, card, (x, y)),
     }
     draw_armor(context, game.get_armor_at(pos), (x, y));
 }
 
+// Return the area of the given line of text below the draw pile, counting
+// from 0.
+fn sidebar_line(line: i32) -> Rect {
+    Rect::new(DRAW_PILE_POSITION.0, DRAW_PILE_POSITION.1 + CARD_WIDTH as i32 + (UI_SPACE + UI_FONT_HEIGHT as i32) * line, CARD_WIDTH, 0)
+}
+
 fn inside_draw_pile((x, y): (i32, i32)) -> bool {
     x >= DRAW_PILE_POSITION.0 &&
         x <= DRAW_PILE_POSITION.0 + CARD_WIDTH as i32 &&
@@ -183,13 +256,282 @@ fn inside_draw_pile((x, y): (i32, i32)) -> bool {
         y <= DRAW_PILE_POSITION.1 + CARD_WIDTH as i32
 }
 
+const USAGE: &str = "Usage: squaretillery [--seed N [--solve] | --replay FILE | --position TEXT | --puzzle FILE | --daily] [--deck SPEC] [--rules SPEC] [--bot NAME [--speed MS]]";
+
+// How long bots wait between moves, unless --speed is given.
+const DEFAULT_BOT_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Default)]
+struct Options {
+    seed: Option<u64>,           // Deal the game from this seed instead of a random one
+    replay: Option<PathBuf>,     // Play back this replay file instead of playing a game
+    position: Option<Game>,      // Play from this position instead of dealing a new game
+    puzzle: Option<PathBuf>,     // Solve this puzzle instead of playing a game
+    daily: bool,                 // Play today's daily challenge
+    solve: bool,                 // Print the best way to win the deal instead of playing it
+    deck: Option<DeckSpec>,      // Deal a new game from this deck instead of a standard one
+    rules: Option<RuleSet>,      // Play a new game by these rules instead of the classic ones
+    bot: Option<String>,         // Let this bot play the game
+    bot_delay: Option<Duration>, // How long the bot waits between moves
+}
+
+fn parse_options() -> Result<Options, String> {
+    let mut args = std::env::args().skip(1);
+    let mut options = Options::default();
+    while let Some(arg) = args.next() {
+        let mut value = || { args.next().ok_or_else(|| format!("Missing value for {}.", arg)) };
+        match arg.as_str() {
+            "--seed" => {
+                let value = value()?;
+                options.seed = Some(value.parse().map_err(|_| format!("Invalid seed: {}", value))?);
+            },
+            "--replay" => options.replay = Some(PathBuf::from(value()?)),
+            "--position" => options.position = Some(notation::parse_position(&value()?)?),
+            "--puzzle" => options.puzzle = Some(PathBuf::from(value()?)),
+            "--daily" => options.daily = true,
+            "--solve" => options.solve = true,
+            "--deck" => options.deck = Some(save::parse_deck_spec(&value()?.split_whitespace().collect::<Vec<_>>())?),
+            "--rules" => options.rules = Some(save::parse_rules(&value()?.split_whitespace().collect::<Vec<_>>())?),
+            "--bot" => {
+                let value = value()?;
+                if bot::by_name(&value).is_none() {
+                    return Err(format!("Unknown bot: {} (try {})", value, bot::NAMES.join(" or ")));
+                }
+                options.bot = Some(value);
+            },
+            "--speed" => {
+                let value = value()?;
+                options.bot_delay = Some(Duration::from_millis(value.parse().map_err(|_| format!("Invalid speed: {}", value))?));
+            },
+            _ => return Err(format!("Unknown argument: {}", arg)),
+        }
+    }
+
+    // The daily challenge is the same deal for everyone, so it cannot be
+    // changed, and neither a bot nor the solver may show the player how to
+    // play it before their own attempt
+    let conflicting = [
+        ("--seed", options.seed.is_some()), ("--replay", options.replay.is_some()), ("--position", options.position.is_some()),
+        ("--puzzle", options.puzzle.is_some()), ("--solve", options.solve), ("--deck", options.deck.is_some()),
+        ("--rules", options.rules.is_some()), ("--bot", options.bot.is_some()),
+    ];
+    if let Some((name, _)) = conflicting.iter().find(|(_, given)| { options.daily && *given }) {
+        return Err(format!("The daily challenge cannot be played with {}.", name));
+    }
+    Ok(options)
+}
+
+// Make the next move of the replay that is being played back.
+fn step_replay(game: &mut Game, replay: &Replay) {
+    if let Some(action) = replay.actions.get(game.history().len()) {
+        if let Err(error) = game.apply(*action) {
+            eprintln!("Could not play back move {}: {}", game.history().len() + 1, error);
+        }
+    }
+}
+
+// Make a move for the player. The front end only offers legal moves, but if
+// one is not allowed after all, it is reported and nothing happens.
+fn play(game: &mut Game, action: Action) {
+    if let Err(error) = game.apply(action) {
+        eprintln!("{}", error);
+    }
+}
+
+// Return true if the board is covered by the statistics or by the result of the
+// game or puzzle, so that it cannot be played on.
+fn board_hidden(game: &Game, puzzle: Option<&Puzzle>, show_stats: bool) -> bool {
+    show_stats || game.is_game_over() || matches!(puzzle, Some(puzzle) if puzzle.status(game) != GoalStatus::InProgress)
+}
+
+// A bot that thinks on a thread of its own, so that the window keeps
+// responding while it decides on a move.
+struct BotPlayer {
+    name: &'static str,
+    positions: mpsc::Sender<Game>,   // Positions for the bot to decide on
+    moves: mpsc::Receiver<Action>,   // The moves that it decided on
+    thinking: bool,                  // Whether the bot has not answered the last position yet
+}
+
+impl BotPlayer {
+    fn start(mut bot: Box<dyn Strategy + Send>) -> BotPlayer {
+        let name = bot.name();
+        let (positions, requests) = mpsc::channel::<Game>();
+        let (answers, moves) = mpsc::channel();
+        thread::spawn(move || {
+            for game in requests {
+                let action = match game.drawn() {
+                    Some(card) => bot.choose(&game, card),
+                    None => Action::Draw,
+                };
+                if answers.send(action).is_err() { break; }
+            }
+        });
+        BotPlayer { name, positions, moves, thinking: false }
+    }
+
+    // Make the move that the bot decided on, if it has, and otherwise ask it
+    // to decide on one. Returns true if a move was made.
+    fn step(&mut self, game: &mut Game) -> bool {
+        if !self.thinking {
+            self.thinking = self.positions.send(game.clone()).is_ok();
+            return false;
+        }
+        let action = match self.moves.try_recv() {
+            Ok(action) => action,
+            Err(_) => return false,
+        };
+        self.thinking = false;
+        if let Err(error) = game.apply(action) {
+            eprintln!("The {} bot tried an illegal move: {}", self.name, error);
+        }
+        true
+    }
+}
+
+// Print the best way to win the game from its current position, as a replay
+// that can be saved and played back with --replay. Positions and puzzles
+// cannot be replayed, so for those only the moves are printed.
+fn print_solution(game: &Game) {
+    match solver::solve(game, solver::DEFAULT_NODE_BUDGET) {
+        solver::Solution::Solved { shame, actions } => {
+            eprintln!("Seed {} can be won with {} shame.", game.seed(), shame);
+            match Replay::record(game) {
+                Some(mut replay) => {
+                    replay.actions.extend(actions);
+                    print!("{}", replay::write_replay(&replay));
+                },
+                None => for action in actions {
+                    println!("move {}", save::write_action(action));
+                },
+            }
+        },
+        solver::Solution::Unwinnable => eprintln!("Seed {} cannot be won.", game.seed()),
+        solver::Solution::Unknown => eprintln!("Could not solve seed {} within the search limit.", game.seed()),
+    }
+}
+
 pub fn main() {
-    let mut game = Game::new();
-    game.set_up();
+    let options = match parse_options() {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}", message);
+            eprintln!("{}", USAGE);
+            std::process::exit(1);
+        },
+    };
+
+    let replay = options.replay.as_ref().map(|path| {
+        replay::load(path).unwrap_or_else(|error| {
+            eprintln!("Could not load replay: {}", error);
+            std::process::exit(1);
+        })
+    });
+
+    let puzzle: Option<Puzzle> = options.puzzle.as_ref().map(|path| {
+        puzzle::load(path).unwrap_or_else(|error| {
+            eprintln!("Could not load puzzle: {}", error);
+            std::process::exit(1);
+        })
+    });
+
+    // Each player only gets one attempt at the daily challenge
+    let today = daily::Date::today();
+    if options.daily {
+        if let Some(result) = daily::records_path().and_then(|path| { daily::attempt(&path, today) }) {
+            println!("You have already played the daily challenge for {}: {}", today, result);
+            return;
+        }
+    }
+
+    let mut bot = options.bot.as_ref().and_then(|name| { bot::by_name(name) }).map(BotPlayer::start);
+    let bot_delay = options.bot_delay.unwrap_or(DEFAULT_BOT_DELAY);
+    let mut last_bot_move = Instant::now();
+
+    // The move that the player was recommended, along with the moves that had
+    // been made when it was asked for, so that it is hidden once the game moves
+    // on
+    let mut hint: Option<(Vec<Action>, bot::Hint)> = None;
+
+    // Resume the saved game, unless the player asked for a specific deal,
+    // position, deck or rules, or is letting a bot play. The game is only
+    // saved over the saved game if it was resumed from it, so that a game in
+    // progress is not lost by starting a different one; a saved game that
+    // cannot be loaded is replaced.
+    let save_path = paths::data_dir().map(|dir| dir.join("save.txt"));
+    let new_game_asked = options.seed.is_some() || options.position.is_some() || puzzle.is_some() || options.daily ||
+        options.deck.is_some() || options.rules.is_some();
+    let (saved_game, owns_save) = match (&save_path, &replay) {
+        (Some(path), None) if path.exists() && !new_game_asked && !options.solve && bot.is_none() => {
+            match save::load(path) {
+                Ok(game) => (Some(game), true),
+                Err(error) => {
+                    eprintln!("Could not resume saved game, so it will be replaced: {}", error);
+                    (None, true)
+                },
+            }
+        },
+        (Some(path), _) => (None, !path.exists()),
+        (None, _) => (None, false),
+    };
+
+    let mut game = match (saved_game, &replay, &puzzle, &options.position) {
+        (_, Some(replay), _, _) => {
+            println!("Playing back replay of seed {}", replay.seed);
+            replay.start().unwrap_or_else(|error| {
+                eprintln!("Could not start replay: {}", error);
+                std::process::exit(1);
+            })
+        },
+        (Some(game), None, _, _) => {
+            println!("Resuming saved game with seed {}", game.seed());
+            game
+        },
+        (None, None, Some(puzzle), _) => {
+            println!("{}: {}", puzzle.name, puzzle.goal);
+            puzzle.start.clone()
+        },
+        (None, None, None, Some(position)) => {
+            println!("Playing from the given position");
+            position.clone()
+        },
+        (None, None, None, None) if options.daily => {
+            println!("Playing the daily challenge for {}", today);
+            today.game()
+        },
+        (None, None, None, None) => {
+            let deck = options.deck.clone().unwrap_or_default();
+            let mut game = Game::with_deck(deck, options.seed.unwrap_or_else(rand::random))
+                .expect("The deck should have been checked when parsing the options.")
+                .with_rules(options.rules.unwrap_or_default());
+            game.set_up().expect("The deck should contain enough cards to set up the board.");
+            println!("Playing seed {}", game.seed());
+            game
+        },
+    };
+
+    if options.solve {
+        print_solution(&game);
+        return;
+    }
 
     let mut dragged_card: Option<Card> = None;
     let mut dragged_offset: Option<(i32, i32)> = None;
 
+    // Only the player's own games count in the statistics, which are updated
+    // as soon as the game ends
+    let stats_path = stats::path().filter(|_| {
+        replay.is_none() && bot.is_none() && options.position.is_none() && puzzle.is_none()
+    });
+    let mut stats = stats::path().map(|path| {
+        stats::load(&path).unwrap_or_else(|error| {
+            eprintln!("Could not load statistics: {}", error);
+            Stats::default()
+        })
+    }).unwrap_or_default();
+    let mut game_recorded = game.is_game_over();
+    let mut show_stats = false;
+
     let sdl = sdl2::init().unwrap();
     let video = sdl.video().unwrap();
     let ttf = sdl2::ttf::init().unwrap();
@@ -209,7 +551,7 @@ pub fn main() {
 
     let mut event_pump = sdl.event_pump().unwrap();
 
-    let mut context = DrawContext {
+    let context = &mut DrawContext {
         canvas: &mut canvas,
         texture_creator: &texture_creator,
         ui_font: &ui_font,
@@ -220,59 +562,131 @@ pub fn main() {
     let cursor_hand = Cursor::from_system(SystemCursor::Hand).unwrap();
 
     'running: loop {
+        if let Some(bot) = &mut bot {
+            if !game.is_game_over() && last_bot_move.elapsed() >= bot_delay && bot.step(&mut game) {
+                last_bot_move = Instant::now();
+            }
+        }
+
         for event in event_pump.poll_iter() {
             match event {
                 Event::Quit {..} |
                 Event::KeyDown { keycode: Some(Keycode::Escape), .. } => {
                     break 'running
                 },
-                Event::MouseButtonDown { x, y, mouse_btn: MouseButton::Left, .. } => {
-                    if inside_draw_pile((x, y)) {
-                        if let Some(card) = game.drawn() {
-                            dragged_card = Some(card);
-                            dragged_offset = Some((x - DRAW_PILE_POSITION.0, y - DRAW_PILE_POSITION.1));
-                        } else {
-                            game.draw().unwrap();
-                        }
+                Event::KeyDown { keycode: Some(Keycode::Right), .. } |
+                Event::KeyDown { keycode: Some(Keycode::Space), .. } if replay.is_some() => {
+                    if let Some(replay) = &replay { step_replay(&mut game, replay); }
+                },
+                Event::KeyDown { keycode: Some(Keycode::Left), .. } if replay.is_some() => {
+                    game.undo();
+                },
+                Event::KeyDown { keycode: Some(Keycode::S), .. } => {
+                    show_stats = !show_stats;
+                },
+                Event::KeyDown { keycode: Some(Keycode::P), .. } => {
+                    println!("Position: {}", notation::write_position(&game));
+                },
+                Event::KeyDown { keycode: Some(Keycode::R), .. } if puzzle.is_some() && bot.is_none() => {
+                    if let Some(puzzle) = &puzzle { game = puzzle.start.clone(); }
+                    hint = None;
+                    dragged_card = None;
+                    dragged_offset = None;
+                },
+                _ if replay.is_some() => {},  // The player cannot make moves while watching a replay
+                _ if bot.is_some() => {},     // ...or while a bot is playing
+                Event::KeyDown { keycode: Some(Keycode::Z), keymod, .. } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) && !options.daily => {
+                    game.undo();
+                    dragged_card = None;
+                    dragged_offset = None;
+                },
+                Event::KeyDown { keycode: Some(Keycode::H), .. } if !options.daily => {
+                    hint = bot::hint(&game, &mut bot::Greedy, |pos| { pos.to_string() }).map(|hint| { (game.history(), hint) });
+                },
+                Event::KeyDown { keycode: Some(Keycode::Y), keymod, .. } if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) && !options.daily => {
+                    game.redo();
+                    dragged_card = None;
+                    dragged_offset = None;
+                },
+                Event::MouseButtonDown { .. } | Event::MouseButtonUp { .. } if board_hidden(&game, puzzle.as_ref(), show_stats) => {
+                    dragged_card = None;
+                    dragged_offset = None;
+                },
+                Event::MouseButtonDown { x, y, mouse_btn: MouseButton::Left, .. } if inside_draw_pile((x, y)) => {
+                    if let Some(card) = game.drawn() {
+                        dragged_card = Some(card);
+                        dragged_offset = Some((x - DRAW_PILE_POSITION.0, y - DRAW_PILE_POSITION.1));
+                    } else {
+                        play(&mut game, Action::Draw);
                     }
                 },
                 Event::MouseButtonUp { x, y, mouse_btn: MouseButton::Left, .. } => {
-                    translate_screen_to_board((x, y)).map(|pos| {
+                    if let Some(pos) = translate_screen_to_board((x, y)) {
                         if game.can_place_at(pos) {
-                            game.place_card_at(pos).unwrap();
+                            play(&mut game, Action::Place(pos));
                         }
-                    });
+                    }
 
                     dragged_card = None;
                     dragged_offset = None;
                 },
-                Event::MouseButtonUp { x, y, mouse_btn: MouseButton::Right, .. } => {
-                    if inside_draw_pile((x, y)) && game.drawn().is_some() {
-                        game.add_to_shame_pile();
-                    }
+                Event::MouseButtonUp { x, y, mouse_btn: MouseButton::Right, .. } if inside_draw_pile((x, y)) && game.drawn().is_some() => {
+                    play(&mut game, Action::Shame);
                 },
                 _ => {}
             }
         }
 
+        if !game_recorded && game.is_game_over() {
+            if let Some(path) = &stats_path {
+                match stats::record(path, &game.score()) {
+                    Ok(updated) => stats = updated,
+                    Err(error) => eprintln!("Could not record statistics: {}", error),
+                }
+            }
+            game_recorded = true;
+        }
+
         context.canvas.set_draw_color(Color::RGB(0xF2, 0xEB, 0xE8));
         context.canvas.clear();
 
-        if game.is_game_over() {
-            (|context: &mut DrawContext| {
-                draw_text_align(context, context.card_font, "GAME OVER", Color::RGB(0x62, 0x5B, 0x58),
+        let puzzle_status = puzzle.as_ref().map(|puzzle| { puzzle.status(&game) });
+        if show_stats {
+            draw_stats(context, &stats);
+        } else if let (Some(puzzle), Some(status)) = (&puzzle, puzzle_status.filter(|status| { *status != GoalStatus::InProgress })) {
+            let (title, color) = match status {
+                GoalStatus::Met => ("SOLVED", Color::RGB(0x74, 0x98, 0x70)),
+                _ => ("NOT SOLVED", Color::RGB(0xC2, 0x7B, 0x78)),
+            };
+            draw_text_align(context, context.card_font, title, Color::RGB(0x62, 0x5B, 0x58),
+                            Rect::new(0, 0, WINDOW_WIDTH, WINDOW_HEIGHT),
+                            AlignH::Center, AlignV::Middle, 0, 0);
+            let lines = [format!("GOAL: {}", puzzle.goal).to_uppercase(), "PRESS R TO RETRY".to_string()];
+            for (i, line) in lines.iter().enumerate() {
+                draw_text_align(context, context.ui_font, line, if i == 0 { color } else { Color::RGB(0x82, 0x7B, 0x78) },
                                 Rect::new(0, 0, WINDOW_WIDTH, WINDOW_HEIGHT),
-                                AlignH::Center, AlignV::Middle, 0, 0);
-                if game.get_shame() == 0 {
-                    draw_text_align(context, context.ui_font, "PERFECT GAME", Color::RGB(0x74, 0x98, 0x70),
-                                    Rect::new(0, 0, WINDOW_WIDTH, WINDOW_HEIGHT),
-                                    AlignH::Center, AlignV::Middle, 0, CARD_FONT_HEIGHT as i32 + UI_SPACE as i32);
-                } else {
-                    draw_text_align(context, context.ui_font, &format!("{} SHAME", game.get_shame()), Color::RGB(0xC2, 0x7B, 0x78),
-                                    Rect::new(0, 0, WINDOW_WIDTH, WINDOW_HEIGHT),
-                                    AlignH::Center, AlignV::Middle, 0, CARD_FONT_HEIGHT as i32 + UI_SPACE as i32);
-                }
-            })(&mut context);
+                                AlignH::Center, AlignV::Middle, 0, CARD_FONT_HEIGHT as i32 + UI_SPACE + (UI_FONT_HEIGHT as i32 + UI_SPACE) * i as i32);
+            }
+        } else if let Some(outcome) = game.outcome() {
+            let title = match outcome {
+                GameOutcome::Victory => "VICTORY",
+                GameOutcome::Defeat => "OUT OF CARDS",
+                GameOutcome::Stuck => "NO MOVES LEFT",
+            };
+            draw_text_align(context, context.card_font, title, Color::RGB(0x62, 0x5B, 0x58),
+                            Rect::new(0, 0, WINDOW_WIDTH, WINDOW_HEIGHT),
+                            AlignH::Center, AlignV::Middle, 0, 0);
+
+            let score = game.score();
+            let summary_color = if score.is_win() { Color::RGB(0x74, 0x98, 0x70) } else { Color::RGB(0xC2, 0x7B, 0x78) };
+            draw_text_align(context, context.ui_font, &score.summary(), summary_color,
+                            Rect::new(0, 0, WINDOW_WIDTH, WINDOW_HEIGHT),
+                            AlignH::Center, AlignV::Middle, 0, CARD_FONT_HEIGHT as i32 + UI_SPACE);
+            for (i, line) in score.breakdown().iter().enumerate() {
+                draw_text_align(context, context.ui_font, line, Color::RGB(0x82, 0x7B, 0x78),
+                                Rect::new(0, 0, WINDOW_WIDTH, WINDOW_HEIGHT),
+                                AlignH::Center, AlignV::Middle, 0, CARD_FONT_HEIGHT as i32 + UI_SPACE + (UI_FONT_HEIGHT as i32 + UI_SPACE) * (i as i32 + 2));
+            }
         } else {
             // Update mouse cursor
 
@@ -281,7 +695,9 @@ pub fn main() {
                 (mouse_state.x(), mouse_state.y())
             };
 
-            if inside_draw_pile((mouse_x, mouse_y)) && game.drawn().is_none() {
+            let legal_actions = game.legal_actions();
+
+            if inside_draw_pile((mouse_x, mouse_y)) && legal_actions.contains(&LegalAction::Draw) {
                 cursor_hand.set();
             } else {
                 cursor_default.set();
@@ -290,40 +706,135 @@ pub fn main() {
             // Render current (drawn) card
 
             if dragged_card.is_none() {
-                draw_card(&mut context, game.drawn(), DRAW_PILE_POSITION);
+                draw_card(context, game.drawn(), DRAW_PILE_POSITION);
             } else {
-                draw_card(&mut context, None, DRAW_PILE_POSITION);
+                draw_card(context, None, DRAW_PILE_POSITION);
             }
 
-            (|context: &mut DrawContext| {
-                draw_text_align(context, context.ui_font, &format!("{} LEFT", game.cards_left()), Color::RGB(0x82, 0x7B, 0x78),
-                                Rect::new(DRAW_PILE_POSITION.0, DRAW_PILE_POSITION.1 + CARD_WIDTH as i32, CARD_WIDTH, 0),
-                                AlignH::Center, AlignV::Top, 0, UI_SPACE);
-            })(&mut context);
+            draw_text_align(context, context.ui_font, &format!("{} LEFT", game.cards_left()), Color::RGB(0x82, 0x7B, 0x78),
+                            sidebar_line(0), AlignH::Center, AlignV::Top, 0, UI_SPACE);
+
+            // Render royals killed
+
+            if game.royals_killed() > 0 {
+                draw_text_align(context, context.ui_font, &format!("{}/{} KILLED", game.royals_killed(), game.royals_total()), Color::RGB(0x74, 0x98, 0x70),
+                                sidebar_line(1), AlignH::Center, AlignV::Top, 0, UI_SPACE);
+            }
 
             // Render shame
 
             if game.get_shame() > 0 {
-                (|context: &mut DrawContext| {
-                    draw_text_align(context, context.ui_font, &format!("{} SHAME", game.get_shame()), Color::RGB(0xC2, 0x7B, 0x78),
-                                    Rect::new(DRAW_PILE_POSITION.0, DRAW_PILE_POSITION.1 + CARD_WIDTH as i32 + UI_SPACE + UI_FONT_HEIGHT as i32, CARD_WIDTH, 0),
-                                    AlignH::Center, AlignV::Top, 0, UI_SPACE);
-                })(&mut context);
+                draw_text_align(context, context.ui_font, &format!("{} SHAME", game.get_shame()), Color::RGB(0xC2, 0x7B, 0x78),
+                                sidebar_line(2), AlignH::Center, AlignV::Top, 0, UI_SPACE);
+            }
+
+            // Render replay progress
+
+            if let Some(replay) = &replay {
+                draw_text_align(context, context.ui_font, &format!("MOVE {}/{}", game.history().len(), replay.actions.len()), Color::RGB(0x82, 0x7B, 0x78),
+                                sidebar_line(3), AlignH::Center, AlignV::Top, 0, UI_SPACE);
+            }
+
+            // Render puzzle progress
+
+            if let Some(puzzle) = &puzzle {
+                draw_text_align(context, context.ui_font, &puzzle.goal.progress(&game), Color::RGB(0x82, 0x7B, 0x78),
+                                sidebar_line(3), AlignH::Center, AlignV::Top, 0, UI_SPACE);
             }
 
-            // Render board
+            // Render the reason for the hint
+
+            let hint = hint.as_ref().filter(|(history, _)| { *history == game.history() }).map(|(_, hint)| { hint });
+            if let Some(hint) = hint {
+                for (i, line) in wrap_text(context.ui_font, &hint.reason.to_uppercase(), CARD_WIDTH).iter().enumerate() {
+                    draw_text_align(context, context.ui_font, line, Color::RGB(0x60, 0x90, 0xB8),
+                                    sidebar_line(4 + i as i32), AlignH::Center, AlignV::Top, 0, UI_SPACE);
+                }
+            }
+
+            // Render graveyard
+
+            draw_graveyard(context, &game);
+
+            // Render board, highlighting where the dragged card can be placed,
+            // or otherwise the hinted tile
 
             for pos in BoardPosition::all_valid() {
-                draw_card_on_board(&mut context, &game, pos, dragged_card.is_some());
+                let is_legal = legal_actions.iter().any(|legal| { legal.action() == Action::Place(pos) });
+                let is_active = match (dragged_card, hint) {
+                    (Some(_), _) => Some(is_legal),
+                    (None, Some(hint)) if hint.action != Action::Shame => Some(hint.action == Action::Place(pos)),
+                    (None, _) => None,
+                };
+                draw_card_on_board(context, &game, pos, is_active);
+            }
+
+            // Render the health of each royal, and which royals would be
+            // killed if the dragged card was dropped
+
+            let previews = match translate_screen_to_board((mouse_x, mouse_y)) {
+                Some(pos) if dragged_card.is_some() && game.can_place_at(pos) => game.preview(pos),
+                _ => vec![],
+            };
+            for status in game.royal_statuses() {
+                let dies = previews.iter().any(|attack| { attack.royal == status.position && attack.dies });
+                draw_royal_health(context, &status, dies);
             }
 
             // Render card being dragged
 
-            dragged_card.map(|card| {
-                draw_card(&mut context, Some(card), (event_pump.mouse_state().x() - dragged_offset.unwrap().0, event_pump.mouse_state().y() - dragged_offset.unwrap().1));
-            });
+            if let (Some(card), Some(offset)) = (dragged_card, dragged_offset) {
+                draw_card(context, Some(card), (event_pump.mouse_state().x() - offset.0, event_pump.mouse_state().y() - offset.1));
+            }
         }
 
         context.canvas.present();
     }
+
+    // Games played back from a replay, played by a bot, or played from a given
+    // position or puzzle are not kept; the last of these have no seed to
+    // replay them from
+    if replay.is_some() || bot.is_some() || options.position.is_some() || puzzle.is_some() { return; }
+
+    // The daily challenge is over once the window is closed, whether or not
+    // the game was finished
+    if options.daily {
+        if let Some(path) = daily::records_path() {
+            if let Err(error) = daily::record(&path, today, &game.score()) {
+                eprintln!("Could not record the daily challenge: {}", error);
+            }
+        }
+        println!("{}", daily::result_line(today, &game.score()));
+        return;
+    }
+
+    // Save the game so that it can be resumed next time. Finished games are
+    // not kept, but are recorded as replays instead.
+    if game.is_game_over() {
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+        let path = paths::data_dir().map(|dir| dir.join("replays").join(format!("{}-{}.txt", timestamp, game.seed())));
+        if let (Some(path), Some(replay)) = (path, Replay::record(&game)) {
+            match replay::save(&replay, &path) {
+                Ok(()) => println!("Recorded replay in {}", path.display()),
+                Err(error) => eprintln!("Could not record replay: {}", error),
+            }
+        }
+    }
+
+    match save_path {
+        Some(path) if owns_save => {
+            let result = if game.is_game_over() {
+                if path.exists() { std::fs::remove_file(&path).map_err(save::SaveError::from) } else { Ok(()) }
+            } else {
+                save::save(&game, &path)
+            };
+            if let Err(error) = result {
+                eprintln!("Could not save game: {}", error);
+            }
+        },
+        Some(path) if !game.is_game_over() => {
+            println!("This game was not saved, so that the game saved in {} can still be resumed.", path.display());
+        },
+        _ => {},
+    }
 }
@@ -0,0 +1,185 @@
+// A one-line notation for a whole position, which can be pasted into a chat or
+// an issue to show someone exactly where a game is at, in the spirit of FEN in
+// chess. It is made of seven fields separated by spaces:
+//
+//     -,-,-/-,3S,JK,2C,-/-,7D.9H,-,4H,KH+3/-,AD,10C,5S,-/-,-,- QS 8C.2D.JS - 1 12 classic
+//
+// 1. The board, row by row from the top, with the rows separated by slashes and
+//    the tiles in each row by commas. The top and bottom rows have no corners,
+//    so they only have three tiles. Each tile is its pile of cards from the
+//    bottom to the top, separated by dots, or `-` if it is empty, followed by
+//    `+` and the armor of the royal on it, if it has any.
+// 2. The drawn card, or `-` if no card is drawn.
+// 3. The deck from the bottom to the top, separated by dots, or `-` if it is
+//    empty. The top card is the next one to be drawn.
+// 4. The royals that were killed, in the order they were killed, each with its
+//    armor like the tiles on the board, or `-` if none were.
+// 5. The number of cards on the shame pile.
+// 6. The number of royals that have to be killed to win.
+// 7. The rules, as in save files, with commas instead of spaces.
+//
+// Cards are written as by `Card`'s Display. The moves that led to the position
+// are not part of it, so a game that is loaded from its notation has no
+// history and no seed.
+
+use crate::game::{Board, BoardPosition, Card, Game, KilledRoyal, Pile};
+use crate::save::{parse_rules, write_rules};
+
+const FIELDS: usize = 7;
+
+pub fn write_position(game: &Game) -> String {
+    let mut rows = vec![];
+    for y in -2..=2 {
+        let tiles: Vec<String> = (-2..=2).filter_map(|x| { BoardPosition::new((x, y)).ok() }).map(|pos| {
+            write_tile(game.board().get_pile_at(pos).cards(), game.get_armor_at(pos))
+        }).collect();
+        rows.push(tiles.join(","));
+    }
+
+    let graveyard: Vec<String> = game.graveyard().iter().map(|killed| { write_tile(&[killed.card], killed.armor) }).collect();
+    let fields = [
+        rows.join("/"),
+        game.drawn().map(|card| { card.to_string() }).unwrap_or_else(|| "-".to_string()),
+        write_cards(game.deck().cards()),
+        if graveyard.is_empty() { "-".to_string() } else { graveyard.join(".") },
+        game.get_shame().to_string(),
+        game.royals_total().to_string(),
+        write_rules(game.rules()).replace(' ', ","),
+    ];
+    fields.join(" ")
+}
+
+pub fn parse_position(text: &str) -> Result<Game, String> {
+    let fields: Vec<&str> = text.split_whitespace().collect();
+    if fields.len() != FIELDS {
+        return Err(format!("A position has {} fields separated by spaces, but this one has {}.", FIELDS, fields.len()));
+    }
+
+    let mut board = Board::new_empty();
+    let rows: Vec<&str> = fields[0].split('/').collect();
+    if rows.len() != 5 { return Err(format!("The board has 5 rows, but this one has {}.", rows.len())); }
+    for (y, row) in (-2..=2).zip(rows) {
+        let positions: Vec<BoardPosition> = (-2..=2).filter_map(|x| { BoardPosition::new((x, y)).ok() }).collect();
+        let tiles: Vec<&str> = row.split(',').collect();
+        if tiles.len() != positions.len() {
+            return Err(format!("Row {} of the board has {} tiles, but this one has {}.", y + 3, positions.len(), tiles.len()));
+        }
+        for (pos, tile) in positions.into_iter().zip(tiles) {
+            let (cards, armor) = parse_tile(tile)?;
+            for card in cards {
+                board.place_card_at(pos, card);
+            }
+            board.add_armor_at(pos, armor);
+        }
+    }
+
+    let drawn = match fields[1] {
+        "-" => None,
+        card => Some(card.parse::<Card>().map_err(|error| { error.to_string() })?),
+    };
+    let deck = Pile::from_cards(parse_cards(fields[2])?);
+
+    let mut graveyard = vec![];
+    if fields[3] != "-" {
+        for killed in fields[3].split('.') {
+            match parse_tile(killed)? {
+                (cards, armor) if cards.len() == 1 && cards[0].is_royal() => {
+                    graveyard.push(KilledRoyal { card: cards[0], armor, move_number: 0 });
+                },
+                _ => return Err(format!("{:?} is not a killed royal.", killed)),
+            }
+        }
+    }
+
+    let shame = fields[4].parse().map_err(|_| { format!("Invalid shame: {}", fields[4]) })?;
+    let royals_total = fields[5].parse().map_err(|_| { format!("Invalid number of royals: {}", fields[5]) })?;
+    let rules = parse_rules(&fields[6].split(',').collect::<Vec<_>>())?;
+
+    let mut game = Game::from_parts(deck, board, drawn, shame, 0, None).with_rules(rules);
+    game.restore_graveyard(graveyard, royals_total);
+    Ok(game)
+}
+
+fn write_cards(cards: &[Card]) -> String {
+    if cards.is_empty() { return "-".to_string(); }
+    cards.iter().map(|card| { card.to_string() }).collect::<Vec<_>>().join(".")
+}
+
+fn parse_cards(text: &str) -> Result<Vec<Card>, String> {
+    if text == "-" { return Ok(vec![]); }
+    text.split('.').map(|card| { card.parse::<Card>().map_err(|error| { error.to_string() }) }).collect()
+}
+
+fn write_tile(cards: &[Card], armor: u8) -> String {
+    if armor > 0 { format!("{}+{}", write_cards(cards), armor) } else { write_cards(cards) }
+}
+
+fn parse_tile(text: &str) -> Result<(Vec<Card>, u8), String> {
+    let mut parts = text.splitn(2, '+');
+    let cards = parse_cards(parts.next().unwrap_or(""))?;
+    let armor = match parts.next() {
+        Some(armor) => armor.parse().ok().filter(|armor| { *armor > 0 }).ok_or_else(|| { format!("Invalid armor: {}", text) })?,
+        None => 0,
+    };
+    Ok((cards, armor))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Partway through a game: an armored royal, piles of several cards, a
+    // drawn card, two royals killed and some shame.
+    const POSITION: &str = "-,-,-/-,3S,JK,2C.5C,-/-,7D.9H,-,4H,KH+3/-,AD,10C,5S,-/-,QD+1,- QS 8C.2D.JS QH+2.JD 2 12 classic";
+
+    fn pos(x: i8, y: i8) -> BoardPosition {
+        BoardPosition::new((x, y)).unwrap()
+    }
+
+    #[test]
+    fn position_round_trips() {
+        let game = parse_position(POSITION).unwrap();
+        assert_eq!(write_position(&game), POSITION);
+
+        assert_eq!(game.get_armor_at(pos(2, 0)), 3);
+        assert_eq!(game.get_armor_at(pos(0, 2)), 1);
+        assert_eq!(game.board().get_pile_at(pos(1, -1)).to_string(), "2C 5C");
+        assert_eq!(game.drawn(), Some("QS".parse().unwrap()));
+        assert_eq!(game.deck().to_string(), "8C 2D JS");
+        assert_eq!(game.graveyard().iter().map(|killed| { (killed.card.to_string(), killed.armor) }).collect::<Vec<_>>(),
+                   vec![("QH".to_string(), 2), ("JD".to_string(), 0)]);
+        assert_eq!((game.get_shame(), game.royals_killed(), game.royals_total()), (2, 2, 12));
+    }
+
+    #[test]
+    fn played_game_round_trips() {
+        let mut game = Game::with_seed(3);
+        game.set_up().unwrap();
+        for _ in 0..40 {
+            let action = game.legal_actions()[0].action();
+            game.apply(action).unwrap();
+        }
+        let text = write_position(&game);
+        assert_eq!(write_position(&parse_position(&text).unwrap()), text);
+    }
+
+    #[test]
+    fn malformed_fields_are_rejected() {
+        let broken = |from: &str, to: &str| {
+            assert!(POSITION.contains(from), "{}", from);
+            parse_position(&POSITION.replacen(from, to, 1)).is_err()
+        };
+        assert!(broken(" classic", ""));                     // Too few fields
+        assert!(broken(" classic", " classic extra"));       // Too many fields
+        assert!(broken("-,-,-/-,3S", "-,-,-/-,-,-/-,3S"));   // Too many rows
+        assert!(broken("-,-,-/-,3S", "-,-,-,-/-,3S"));       // A corner in the top row
+        assert!(broken("-,3S,JK", "-,3S.JK"));               // Too few tiles in a row
+        assert!(broken("3S", "3X"));                         // Not a card
+        assert!(broken("KH+3", "KH+0"));                     // Armor must be more than none
+        assert!(broken("KH+3", "KH+x"));
+        assert!(broken("QH+2.JD", "QH+2.5D"));               // Only royals can be killed
+        assert!(broken(" 2 12", " -2 12"));                  // Shame must be a count
+        assert!(broken(" 12 ", " many "));
+        assert!(broken("classic", "nonsense"));
+    }
+}
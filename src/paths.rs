@@ -0,0 +1,17 @@
+use std::env;
+use std::path::PathBuf;
+
+// Return the directory where the game keeps its files between runs, such as
+// the saved game. This follows the usual conventions of each platform, but
+// without creating the directory.
+pub fn data_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        env::var_os("HOME").map(|home| PathBuf::from(home).join("Library").join("Application Support"))
+    } else {
+        env::var_os("XDG_DATA_HOME").map(PathBuf::from)
+            .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".local").join("share")))
+    };
+    base.map(|base| base.join("squaretillery"))
+}
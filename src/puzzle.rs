@@ -0,0 +1,269 @@
+// Puzzles: positions that are set up by hand, with a known deck and a goal.
+//
+// A puzzle file is plain text, like save files, with one part of the puzzle
+// per line:
+//
+//     squaretillery-puzzle 1
+//     name The last King
+//     rules classic
+//     pile 0 -1 2H 5H
+//     pile -2 0 KH
+//     armor -2 0 3
+//     drawn 9C
+//     deck QS 4H JK
+//     kill all
+//     max-shame 1
+//
+// Piles are given by the coordinates of their board position followed by their
+// cards from the bottom to the top, and the deck from the bottom to the top, so
+// the last card is drawn first. Cards are written like AS, 10H, QD or JK, and
+// any tile that is not mentioned is empty. Royals go on the edge of the board,
+// one to a tile, and only royals can have armor.
+//
+// The goal is to kill all royals, or a number of them, optionally with at most
+// some shame. Leaving out `max-shame` means that any amount of shame will do.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::game::{Board, Game, GameOutcome, Pile};
+use crate::save::{parse_cards, parse_coordinates, parse_header, parse_number, parse_rules, SaveError};
+
+const HEADER: &str = "squaretillery-puzzle";
+const VERSION: u32 = 1;
+
+// What has to be done to solve a puzzle.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Goal {
+    pub kills: Option<u8>,      // How many royals must be killed, or None for all of them
+    pub max_shame: Option<u8>,  // The most shame that is allowed, or None for any amount
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum GoalStatus {
+    InProgress,
+    Met,
+    Failed,  // Too much shame, or the game ended before the goal was met
+}
+
+#[derive(Debug, Clone)]
+pub struct Puzzle {
+    pub name: String,
+    pub start: Game,  // The position that the puzzle starts from, and is retried from
+    pub goal: Goal,
+}
+
+impl Goal {
+    // The number of royals that must be killed in the given game.
+    fn kills_needed(&self, game: &Game) -> u8 {
+        self.kills.unwrap_or_else(|| { game.royals_total() })
+    }
+
+    pub fn status(&self, game: &Game) -> GoalStatus {
+        if matches!(self.max_shame, Some(max) if game.get_shame() > max) {
+            GoalStatus::Failed
+        } else if game.royals_killed() >= self.kills_needed(game) {
+            GoalStatus::Met
+        } else if game.is_game_over() {
+            GoalStatus::Failed
+        } else {
+            GoalStatus::InProgress
+        }
+    }
+
+    // How far the game is towards the goal, such as "GOAL 2/3".
+    pub fn progress(&self, game: &Game) -> String {
+        format!("GOAL {}/{}", game.royals_killed(), self.kills_needed(game))
+    }
+}
+
+// The goal in words, such as "kill all royals with at most 1 shame".
+impl fmt::Display for Goal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kills {
+            None => write!(f, "kill all royals")?,
+            Some(1) => write!(f, "kill 1 royal")?,
+            Some(kills) => write!(f, "kill {} royals", kills)?,
+        }
+        match self.max_shame {
+            None => Ok(()),
+            Some(0) => write!(f, " without any shame"),
+            Some(max) => write!(f, " with at most {} shame", max),
+        }
+    }
+}
+
+impl Puzzle {
+    pub fn status(&self, game: &Game) -> GoalStatus {
+        self.goal.status(game)
+    }
+}
+
+pub fn load(path: &Path) -> Result<Puzzle, SaveError> {
+    parse_puzzle(&fs::read_to_string(path)?)
+}
+
+pub fn parse_puzzle(text: &str) -> Result<Puzzle, SaveError> {
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+    let version = parse_header(lines.next(), HEADER)?;
+    if version != VERSION { return Err(SaveError::UnsupportedVersion(version)); }
+
+    let mut name = None;
+    let mut rules = None;
+    let mut board = Board::new_empty();
+    let mut drawn = None;
+    let mut deck = None;
+    let mut goal = Goal { kills: None, max_shame: None };
+    let mut last_line = 1;
+
+    for (line, text) in lines {
+        last_line = line;
+        let error = |message: String| { SaveError::Parse { line, message } };
+        let words: Vec<&str> = text.split_whitespace().collect();
+        match words.split_first() {
+            None => {},
+            Some((&"name", rest)) => name = Some(rest.join(" ")),
+            Some((&"rules", rest)) => rules = Some(parse_rules(rest).map_err(error)?),
+            Some((&"pile", rest)) => {
+                if rest.len() < 3 { return Err(error("Expected a position and at least one card.".to_string())); }
+                let pos = parse_coordinates(&rest[..2], line)?;
+                if board.get_pile_at(pos).size() > 0 { return Err(error(format!("The pile at {} is given twice.", pos))); }
+                let cards = parse_cards(&rest[2..]).map_err(error)?;
+                match (pos.is_edge(), cards.as_slice()) {
+                    (true, [royal]) if royal.is_royal() => {},
+                    (true, _) => return Err(error("Tiles on the edge can only hold a single royal.".to_string())),
+                    (false, _) if cards.iter().any(|card| { card.is_royal() }) => {
+                        return Err(error("Royals can only be placed on the edge.".to_string()));
+                    },
+                    (false, _) => {},
+                }
+                for card in cards {
+                    board.place_card_at(pos, card);
+                }
+            },
+            Some((&"armor", rest)) => {
+                if rest.len() != 3 { return Err(error("Expected a position and an amount.".to_string())); }
+                let pos = parse_coordinates(&rest[..2], line)?;
+                if !pos.is_edge() || board.get_card_at(pos).is_none() {
+                    return Err(error(format!("There is no royal at {} to add armor to.", pos)));
+                }
+                board.add_armor_at(pos, parse_number(&rest[2..], line)?);
+            },
+            Some((&"drawn", rest)) => {
+                drawn = match parse_cards(rest).map_err(error)?.as_slice() {
+                    [card] => Some(*card),
+                    _ => return Err(error("Expected a single card.".to_string())),
+                };
+            },
+            Some((&"deck", rest)) => deck = Some(parse_cards(rest).map_err(error)?),
+            Some((&"kill", ["all"])) => goal.kills = None,
+            Some((&"kill", rest)) => goal.kills = Some(parse_number(rest, line)?),
+            Some((&"max-shame", rest)) => goal.max_shame = Some(parse_number(rest, line)?),
+            Some((keyword, _)) => return Err(error(format!("Unknown keyword: {}", keyword))),
+        }
+    }
+
+    let deck = Pile::from_cards(deck.ok_or(SaveError::Missing("deck"))?);
+
+    // Every royal in the puzzle counts, whether it is on the board already or
+    // still to come
+    let start = Game::from_parts(deck, board, drawn, 0, 0, None).with_rules(rules.unwrap_or_default());
+    let royals_total = start.royals_total();
+    if goal.kills.map_or(royals_total == 0, |kills| { kills == 0 || kills > royals_total }) {
+        return Err(SaveError::Parse { line: last_line, message: format!("The goal cannot be met with {} royals.", royals_total) });
+    }
+    if start.outcome() == Some(GameOutcome::Stuck) {
+        return Err(SaveError::Parse { line: last_line, message: "No card in the puzzle can be placed anywhere.".to_string() });
+    }
+
+    Ok(Puzzle { name: name.unwrap_or_else(|| "Puzzle".to_string()), start, goal })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::parse_position;
+    use crate::solver::{self, Solution};
+
+    const EMPTY_BOARD: &str = "-,-,-/-,-,-,-,-/-,-,-,-,-/-,-,-,-,-/-,-,-";
+
+    // A puzzle file with the given lines after the header.
+    fn puzzle(lines: &str) -> Result<Puzzle, SaveError> {
+        parse_puzzle(&format!("squaretillery-puzzle 1\n{}\n", lines))
+    }
+
+    // The message of the error that the given puzzle is rejected with.
+    fn rejection(lines: &str) -> String {
+        puzzle(lines).unwrap_err().to_string()
+    }
+
+    // A game on an empty board with one royal to kill in all.
+    fn game(deck: &str, killed: &str, shame: u8) -> Game {
+        parse_position(&format!("{} - {} {} {} 1 classic", EMPTY_BOARD, deck, killed, shame)).unwrap()
+    }
+
+    #[test]
+    fn shipped_puzzles_can_be_solved() {
+        for text in &[include_str!("../puzzles/crossfire.txt"), include_str!("../puzzles/last-king.txt")] {
+            let puzzle = parse_puzzle(text).unwrap();
+            match solver::solve(&puzzle.start, solver::DEFAULT_NODE_BUDGET) {
+                Solution::Solved { shame, .. } => assert!(!matches!(puzzle.goal.max_shame, Some(max) if shame > max), "{}", puzzle.name),
+                solution => panic!("{} cannot be solved: {:?}", puzzle.name, solution),
+            }
+        }
+    }
+
+    #[test]
+    fn edge_tiles_hold_a_single_royal() {
+        assert!(puzzle("pile -2 0 KH\ndeck 5H").is_ok());
+        assert!(rejection("pile -2 0 KH QH\ndeck 5H").contains("only hold a single royal"));
+        assert!(rejection("pile -2 0 5H\ndeck 5H").contains("only hold a single royal"));
+        assert!(rejection("pile 0 0 5H KH\ndeck 5H").contains("Royals can only be placed on the edge"));
+    }
+
+    #[test]
+    fn armor_needs_a_royal() {
+        assert!(puzzle("pile -2 0 KH\narmor -2 0 3\ndeck 5H").is_ok());
+        assert_eq!(rejection("armor -2 0 3\npile -2 0 KH\ndeck 5H"),
+                   "Invalid file on line 2: There is no royal at the middle of the left edge to add armor to.");
+        assert!(rejection("pile 0 0 5H\narmor 0 0 1\npile -2 0 KH\ndeck 5H").contains("no royal at the centre"));
+    }
+
+    #[test]
+    fn piles_are_given_once() {
+        assert_eq!(rejection("pile 0 0 5H\npile 0 0 6H\npile -2 0 KH\ndeck 5H"),
+                   "Invalid file on line 3: The pile at the centre is given twice.");
+    }
+
+    #[test]
+    fn goal_must_be_reachable() {
+        assert!(puzzle("pile -2 0 KH\ndeck QS 5H\nkill 2").is_ok());
+        assert!(rejection("pile -2 0 KH\ndeck 5H\nkill 2").contains("cannot be met with 1 royals"));
+        assert!(rejection("pile -2 0 KH\ndeck 5H\nkill 0").contains("cannot be met"));
+        assert!(rejection("deck 5H").contains("cannot be met with 0 royals"));
+    }
+
+    #[test]
+    fn start_must_not_be_stuck() {
+        assert!(rejection("pile -2 0 JS\ndeck QH").contains("No card in the puzzle can be placed anywhere"));
+    }
+
+    #[test]
+    fn goal_status_follows_the_game() {
+        let goal = Goal { kills: None, max_shame: Some(1) };
+        assert_eq!(goal.status(&game("5H", "-", 0)), GoalStatus::InProgress);
+        assert_eq!(goal.status(&game("5H", "JS", 1)), GoalStatus::Met);
+        assert_eq!(goal.status(&game("5H", "-", 2)), GoalStatus::Failed);   // Too much shame
+        assert_eq!(goal.status(&game("-", "-", 0)), GoalStatus::Failed);    // Out of cards
+        assert_eq!(goal.progress(&game("5H", "-", 0)), "GOAL 0/1");
+    }
+
+    #[test]
+    fn goal_is_described_in_words() {
+        assert_eq!(Goal { kills: Some(1), max_shame: Some(0) }.to_string(), "kill 1 royal without any shame");
+        assert_eq!(Goal { kills: Some(3), max_shame: None }.to_string(), "kill 3 royals");
+        assert_eq!(Goal { kills: None, max_shame: Some(2) }.to_string(), "kill all royals with at most 2 shame");
+    }
+}
@@ -0,0 +1,83 @@
+// Random numbers that are the same for a given seed on every machine and in
+// every build.
+//
+// Deals are worked out from their seed whenever a game is replayed or loaded,
+// so the numbers that a seed gives must never change. The generators in the
+// `rand` crate do not promise that across versions, so deals use SplitMix64
+// and a Fisher-Yates shuffle, which are simple enough to keep here. Changing
+// either of them changes every deal, and needs new versions of the save and
+// replay formats.
+
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> SplitMix64 {
+        SplitMix64 { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        mix(self.state)
+    }
+
+    // Return a number from 0 up to but not including the bound, with every
+    // number equally likely.
+    pub fn below(&mut self, bound: u64) -> u64 {
+        // Numbers from the limit up would make the lowest results more likely,
+        // so they are thrown away
+        let limit = u64::MAX - u64::MAX % bound;
+        loop {
+            let number = self.next_u64();
+            if number < limit { return number % bound; }
+        }
+    }
+
+    // Put the items in a random order.
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            let j = self.below(i as u64 + 1) as usize;
+            items.swap(i, j);
+        }
+    }
+}
+
+// Scramble a number so that numbers that are close together give results that
+// have nothing to do with each other.
+pub fn mix(value: u64) -> u64 {
+    let mut z = value;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^ (z >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_are_fixed_for_a_seed() {
+        // The reference output of SplitMix64 for seed 1234567
+        let mut rng = SplitMix64::new(1234567);
+        let numbers: Vec<u64> = (0..3).map(|_| { rng.next_u64() }).collect();
+        assert_eq!(numbers, vec![6457827717110365317, 3203168211198807973, 9817491932198370423]);
+    }
+
+    #[test]
+    fn below_stays_below_the_bound() {
+        let mut rng = SplitMix64::new(0);
+        for bound in 1..50 {
+            assert!(rng.below(bound) < bound);
+        }
+    }
+
+    #[test]
+    fn shuffle_keeps_every_item() {
+        let mut items: Vec<u32> = (0..54).collect();
+        SplitMix64::new(42).shuffle(&mut items);
+        assert_ne!(items, (0..54).collect::<Vec<_>>());
+        items.sort_unstable();
+        assert_eq!(items, (0..54).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,148 @@
+// Recording games and playing them back.
+//
+// A replay consists of the seed that the game was dealt from, the cards that
+// the deck was made of, the rules it was played by and every move that was
+// made, in order. Replay files are plain text, with one move per line, in the
+// same notation as the deck spec, rules and moves in save files:
+//
+//     squaretillery-replay 1
+//     seed 1234
+//     deck-spec standard
+//     rules classic
+//     draw
+//     place -1 0
+//     draw
+//     shame
+
+use std::fs;
+use std::path::Path;
+
+use crate::game::{Action, Game, GameError};
+use crate::deck::DeckSpec;
+use crate::rules::RuleSet;
+use crate::save::{parse_action, parse_deck_spec, parse_header, parse_number, parse_rules, write_action, write_deck_spec, write_rules, SaveError};
+
+const HEADER: &str = "squaretillery-replay";
+const VERSION: u32 = 1;
+
+#[derive(Debug, Clone)]
+pub struct Replay {
+    pub seed: u64,
+    pub deck_spec: DeckSpec,
+    pub rules: RuleSet,
+    pub actions: Vec<Action>,
+}
+
+impl Replay {
+    // Record the moves that have been made in the given game so far. Games
+    // that were not dealt from a deck spec cannot be replayed, and give None.
+    pub fn record(game: &Game) -> Option<Replay> {
+        let deck_spec = game.deck_spec()?.clone();
+        Some(Replay { seed: game.seed(), deck_spec, rules: *game.rules(), actions: game.history() })
+    }
+
+    // Deal the game that the replay starts from, before any moves are made.
+    pub fn start(&self) -> Result<Game, GameError> {
+        let mut game = Game::with_deck(self.deck_spec.clone(), self.seed)?.with_rules(self.rules);
+        game.set_up()?;
+        Ok(game)
+    }
+
+    // Deal the game and make every move in the replay.
+    pub fn play(&self) -> Result<Game, GameError> {
+        let mut game = self.start()?;
+        for action in &self.actions {
+            game.apply(*action)?;
+        }
+        Ok(game)
+    }
+}
+
+pub fn save(replay: &Replay, path: &Path) -> Result<(), SaveError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, write_replay(replay))?;
+    Ok(())
+}
+
+pub fn load(path: &Path) -> Result<Replay, SaveError> {
+    parse_replay(&fs::read_to_string(path)?)
+}
+
+pub fn write_replay(replay: &Replay) -> String {
+    let mut lines = vec![
+        format!("{} {}", HEADER, VERSION),
+        format!("seed {}", replay.seed),
+        format!("deck-spec {}", write_deck_spec(&replay.deck_spec)),
+        format!("rules {}", write_rules(&replay.rules)),
+    ];
+    for action in &replay.actions {
+        lines.push(write_action(*action));
+    }
+    lines.join("\n") + "\n"
+}
+
+pub fn parse_replay(text: &str) -> Result<Replay, SaveError> {
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+    let version = parse_header(lines.next(), HEADER)?;
+    if version != VERSION { return Err(SaveError::UnsupportedVersion(version)); }
+
+    let mut seed = None;
+    let mut deck_spec = DeckSpec::STANDARD;
+    let mut rules = RuleSet::CLASSIC;
+    let mut actions = vec![];
+
+    for (line, text) in lines {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        match words.split_first() {
+            None => {},
+            Some((&"seed", rest)) => seed = Some(parse_number(rest, line)?),
+            Some((&"deck-spec", rest)) => {
+                deck_spec = parse_deck_spec(rest).map_err(|message| { SaveError::Parse { line, message } })?;
+            },
+            Some((&"rules", rest)) => {
+                rules = parse_rules(rest).map_err(|message| { SaveError::Parse { line, message } })?;
+            },
+            Some(_) => actions.push(parse_action(&words, line)?),
+        }
+    }
+
+    Ok(Replay { seed: seed.ok_or(SaveError::Missing("seed"))?, deck_spec, rules, actions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_game_plays_back_to_the_same_position() {
+        let mut game = Game::with_deck(DeckSpec::NO_JOKERS, 42).unwrap().with_rules(RuleSet::STRICT);
+        game.set_up().unwrap();
+        for _ in 0..40 {
+            let action = game.legal_actions()[0].action();
+            game.apply(action).unwrap();
+        }
+
+        let text = write_replay(&Replay::record(&game).unwrap());
+        let replay = parse_replay(&text).unwrap();
+        assert_eq!(write_replay(&replay), text);
+        assert_eq!((replay.deck_spec.clone(), replay.rules), (DeckSpec::NO_JOKERS, RuleSet::STRICT));
+
+        let played = replay.play().unwrap();
+        assert_eq!(crate::save::write_game(&played), crate::save::write_game(&game));
+    }
+
+    #[test]
+    fn games_dealt_by_hand_are_not_recorded() {
+        let game = Game::from_deck_order("KS 9S 8C 7C 6C 5C 4C 3C 2C".parse().unwrap()).unwrap();
+        assert!(Replay::record(&game).is_none());
+    }
+
+    #[test]
+    fn missing_seed_and_broken_moves_are_errors() {
+        assert!(matches!(parse_replay("squaretillery-replay 1\ndraw\n"), Err(SaveError::Missing("seed"))));
+        assert!(matches!(parse_replay("squaretillery-replay 1\nseed 1\nplace 0\n"), Err(SaveError::Parse { line: 3, .. })));
+    }
+}
@@ -0,0 +1,162 @@
+// The rules that differ between variants of Gridcannon.
+//
+// A `RuleSet` is given to a `Game` when it is created, and decides what it
+// takes to kill each kind of royal, which cards can be stacked on which, what
+// aces and jokers do, and where royals go when they are drawn. There are a few
+// named presets, of which `CLASSIC` is the one that is used unless another is
+// asked for.
+
+use crate::game::{Card, Requirement, Suit};
+
+// How a card can be placed on the cannon.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Stacking {
+    ByValue,  // Like any other card, on top of a lower card (or an equal one, if allowed)
+    Recycle,  // By value, but sending the pile that was there back into the deck
+    Reset,    // On any tile, sending the pile that was there back into the deck
+}
+
+// Where a drawn royal is placed: next to the card on the outer cannon that is
+// most like it, where the two orders below decide what is most like it.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RoyalPlacement {
+    SuitFirst,   // Same suit, then same colour, then highest value
+    ValueFirst,  // Highest value, then same suit, then same colour
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct RuleSet {
+    pub jacks: Requirement,   // What the cards on a cannon need to kill a Jack
+    pub queens: Requirement,
+    pub kings: Requirement,
+    pub equal_stacking: bool, // Whether a card can be stacked on a card of the same value
+    pub aces: Stacking,
+    pub jokers: Stacking,
+    pub royal_placement: RoyalPlacement,
+}
+
+impl RuleSet {
+    pub const CLASSIC: RuleSet = RuleSet {
+        jacks: Requirement::Any,
+        queens: Requirement::SameColor,
+        kings: Requirement::SameSuit,
+        equal_stacking: true,
+        aces: Stacking::Reset,
+        jokers: Stacking::Recycle,
+        royal_placement: RoyalPlacement::SuitFirst,
+    };
+
+    // Jokers can be placed anywhere on the cannon, just like aces.
+    pub const WILD_JOKERS: RuleSet = RuleSet { jokers: Stacking::Reset, ..RuleSet::CLASSIC };
+
+    // Jacks need the same colour, and cards can only be stacked on lower ones.
+    pub const STRICT: RuleSet = RuleSet { jacks: Requirement::SameColor, equal_stacking: false, ..RuleSet::CLASSIC };
+
+    // Royals can be killed by cards of any suit.
+    pub const RELAXED: RuleSet = RuleSet { queens: Requirement::Any, kings: Requirement::Any, ..RuleSet::CLASSIC };
+
+    pub const PRESETS: &'static [(&'static str, RuleSet)] = &[
+        ("classic", RuleSet::CLASSIC),
+        ("wild-jokers", RuleSet::WILD_JOKERS),
+        ("strict", RuleSet::STRICT),
+        ("relaxed", RuleSet::RELAXED),
+    ];
+
+    // Return the preset with the given name, or None if there is no such preset.
+    pub fn preset(name: &str) -> Option<RuleSet> {
+        RuleSet::PRESETS.iter().find(|(preset, _)| { *preset == name }).map(|(_, rules)| { *rules })
+    }
+
+    pub fn preset_names() -> Vec<&'static str> {
+        RuleSet::PRESETS.iter().map(|(name, _)| { *name }).collect()
+    }
+
+    // Return the name of the preset that these rules are, if any.
+    pub fn preset_name(&self) -> Option<&'static str> {
+        RuleSet::PRESETS.iter().find(|(_, rules)| { rules == self }).map(|(name, _)| { *name })
+    }
+
+    pub fn requirement_for(&self, royal: Card) -> Requirement {
+        match royal.value() {
+            11 => self.jacks,
+            12 => self.queens,
+            _ => self.kings,
+        }
+    }
+
+    pub fn stacking_of(&self, card: Card) -> Stacking {
+        match (card.value(), card.suit()) {
+            (_, Suit::Joker) => self.jokers,
+            (1, _) => self.aces,
+            _ => Stacking::ByValue,
+        }
+    }
+
+    // Return true if the card can be placed on top of the other one on the
+    // cannon.
+    pub fn can_stack(&self, card: Card, on: Card) -> bool {
+        match self.stacking_of(card) {
+            Stacking::Reset => true,
+            Stacking::ByValue | Stacking::Recycle if self.equal_stacking => card.value() >= on.value(),
+            Stacking::ByValue | Stacking::Recycle => card.value() > on.value(),
+        }
+    }
+}
+
+impl Default for RuleSet {
+    fn default() -> RuleSet {
+        RuleSet::CLASSIC
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn card(text: &str) -> Card {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn presets_are_found_by_name() {
+        for name in RuleSet::preset_names() {
+            assert_eq!(RuleSet::preset(name).unwrap().preset_name(), Some(name));
+        }
+        assert_eq!(RuleSet::preset("classic"), Some(RuleSet::CLASSIC));
+        assert_eq!(RuleSet::preset("easy"), None);
+        assert_eq!(RuleSet { aces: Stacking::ByValue, ..RuleSet::CLASSIC }.preset_name(), None);
+    }
+
+    #[test]
+    fn aces_and_jokers_stack_by_their_own_rules() {
+        let rules = RuleSet::CLASSIC;
+        assert_eq!(rules.stacking_of(card("AS")), Stacking::Reset);
+        assert_eq!(rules.stacking_of(card("JK")), Stacking::Recycle);
+        assert_eq!(rules.stacking_of(card("7D")), Stacking::ByValue);
+        assert_eq!(RuleSet::WILD_JOKERS.stacking_of(card("JK")), Stacking::Reset);
+    }
+
+    #[test]
+    fn cards_stack_on_lower_or_equal_cards() {
+        let rules = RuleSet::CLASSIC;
+        assert!(rules.can_stack(card("6H"), card("5S")));
+        assert!(rules.can_stack(card("5H"), card("5S")));
+        assert!(!rules.can_stack(card("4H"), card("5S")));
+        assert!(rules.can_stack(card("AH"), card("10S")));
+        assert!(!rules.can_stack(card("JK"), card("2S")));
+        assert!(RuleSet::WILD_JOKERS.can_stack(card("JK"), card("2S")));
+
+        // Strict rules do not allow equal cards
+        assert!(!RuleSet::STRICT.can_stack(card("5H"), card("5S")));
+        assert!(RuleSet::STRICT.can_stack(card("6H"), card("5S")));
+    }
+
+    #[test]
+    fn requirements_depend_on_the_royal() {
+        let rules = RuleSet::CLASSIC;
+        assert_eq!(rules.requirement_for(card("JH")), Requirement::Any);
+        assert_eq!(rules.requirement_for(card("QH")), Requirement::SameColor);
+        assert_eq!(rules.requirement_for(card("KH")), Requirement::SameSuit);
+        assert_eq!(RuleSet::RELAXED.requirement_for(card("KH")), Requirement::Any);
+    }
+}
@@ -0,0 +1,423 @@
+// Saving and loading games that are in progress.
+//
+// A save file is a plain text file. The first line identifies the format and
+// its version, and each of the following lines holds one part of the game:
+//
+//     squaretillery-save 1
+//     seed 1234
+//     deck-spec standard
+//     rules classic
+//     shame 1
+//     drawn QH
+//     deck 3S JK 7D AC
+//     pile -1 0 4S 9H
+//     armor 2 0 5
+//     move draw
+//     move place -1 0
+//
+// Cards are written like AS, 10H, QD or JK for a joker, as by `Card`'s
+// Display. The deck and each pile are listed from the bottom
+// to the top, and piles and armor are given by the coordinates of their board
+// position. Empty piles and positions without armor are left out, and `drawn -`
+// means that no card is drawn.
+//
+// The cards that the deck was made of are given by the name of their preset,
+// or as a list such as `decks=2 jokers=0 without=2,3,spades`, where the values
+// and suits after `without` are taken out of every deck.
+//
+// The rules are given by the name of their preset, or if they are not one of
+// the presets, as a list such as `jacks=color queens=color kings=suit
+// equal-stacking=no aces=reset jokers=recycle royals=suit`, where anything left
+// out is as in the classic rules.
+//
+// The moves that were made since the game was dealt from its seed are listed
+// at the end, so that the game can be replayed and moves can still be undone
+// after loading it.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::deck::DeckSpec;
+use crate::game::{Action, Board, BoardPosition, Card, Game, Pile, Requirement, Suit};
+use crate::replay::Replay;
+use crate::rules::{RoyalPlacement, RuleSet, Stacking};
+
+const HEADER: &str = "squaretillery-save";
+const VERSION: u32 = 1;
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(io::Error),
+    UnsupportedVersion(u32),
+    Parse { line: usize, message: String },
+    Missing(&'static str),
+    Inconsistent,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        use SaveError::*;
+        match self {
+            Io(error) => write!(f, "Could not access file: {}", error),
+            UnsupportedVersion(version) => write!(f, "File has version {}, which is not supported.", version),
+            Parse { line, message } => write!(f, "Invalid file on line {}: {}", line, message),
+            Missing(keyword) => write!(f, "Invalid file: missing {}.", keyword),
+            Inconsistent => write!(f, "Invalid save file: the moves do not lead to the saved position."),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<io::Error> for SaveError {
+    fn from(error: io::Error) -> Self { SaveError::Io(error) }
+}
+
+pub fn save(game: &Game, path: &Path) -> Result<(), SaveError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, write_game(game))?;
+    Ok(())
+}
+
+pub fn load(path: &Path) -> Result<Game, SaveError> {
+    parse_game(&fs::read_to_string(path)?)
+}
+
+pub fn write_game(game: &Game) -> String {
+    let mut lines = vec![format!("{} {}", HEADER, VERSION)];
+    lines.append(&mut write_position(game));
+    for action in game.history() {
+        lines.push(format!("move {}", write_action(action)));
+    }
+    lines.join("\n") + "\n"
+}
+
+// Write every line that describes the current position of the game, leaving
+// out the header and the moves.
+fn write_position(game: &Game) -> Vec<String> {
+    let mut lines = vec![];
+
+    lines.push(format!("seed {}", game.seed()));
+    if let Some(spec) = game.deck_spec() {
+        lines.push(format!("deck-spec {}", write_deck_spec(spec)));
+    }
+    lines.push(format!("rules {}", write_rules(game.rules())));
+    lines.push(format!("shame {}", game.get_shame()));
+    lines.push(format!("drawn {}", game.drawn().map(|card| { card.to_string() }).unwrap_or_else(|| "-".to_string())));
+    lines.push(format!("deck {}", write_cards(game.deck().cards())));
+
+    for pos in BoardPosition::all_valid() {
+        let pile = game.board().get_pile_at(pos);
+        if pile.size() > 0 {
+            lines.push(format!("pile {} {} {}", pos.x(), pos.y(), write_cards(pile.cards())));
+        }
+    }
+
+    for pos in BoardPosition::all_valid() {
+        let armor = game.get_armor_at(pos);
+        if armor > 0 {
+            lines.push(format!("armor {} {} {}", pos.x(), pos.y(), armor));
+        }
+    }
+
+    lines
+}
+
+pub fn parse_game(text: &str) -> Result<Game, SaveError> {
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+    let version = parse_header(lines.next(), HEADER)?;
+    if version != VERSION { return Err(SaveError::UnsupportedVersion(version)); }
+
+    let mut seed = None;
+    let mut deck_spec = DeckSpec::STANDARD;
+    let mut rules = RuleSet::CLASSIC;
+    let mut shame = 0;
+    let mut drawn = None;
+    let mut deck = None;
+    let mut board = Board::new_empty();
+    let mut moves = vec![];
+
+    for (line, text) in lines {
+        let error = |message: String| { SaveError::Parse { line, message } };
+        let words: Vec<&str> = text.split_whitespace().collect();
+        match words.split_first() {
+            None => {},
+            Some((&"seed", rest)) => seed = Some(parse_number(rest, line)?),
+            Some((&"deck-spec", rest)) => deck_spec = parse_deck_spec(rest).map_err(error)?,
+            Some((&"rules", rest)) => rules = parse_rules(rest).map_err(error)?,
+            Some((&"shame", rest)) => shame = parse_number(rest, line)?,
+            Some((&"drawn", rest)) => {
+                drawn = match rest {
+                    ["-"] => None,
+                    [card] => Some(card.parse::<Card>().map_err(|invalid| { error(invalid.to_string()) })?),
+                    _ => return Err(error("Expected a single card or -.".to_string())),
+                };
+            },
+            Some((&"deck", rest)) => deck = Some(parse_cards(rest).map_err(error)?),
+            Some((&"pile", rest)) => {
+                if rest.len() < 2 { return Err(error("Expected a position.".to_string())); }
+                let pos = parse_coordinates(&rest[..2], line)?;
+                for card in parse_cards(&rest[2..]).map_err(error)? {
+                    board.place_card_at(pos, card);
+                }
+            },
+            Some((&"armor", rest)) => {
+                if rest.len() != 3 { return Err(error("Expected a position and an amount.".to_string())); }
+                let pos = parse_coordinates(&rest[..2], line)?;
+                board.add_armor_at(pos, parse_number(&rest[2..], line)?);
+            },
+            Some((&"move", rest)) => moves.push(parse_action(rest, line)?),
+            Some((keyword, _)) => return Err(error(format!("Unknown keyword: {}", keyword))),
+        }
+    }
+
+    let seed = seed.ok_or(SaveError::Missing("seed"))?;
+    let deck = deck.ok_or(SaveError::Missing("deck"))?;
+
+    let game = Game::from_parts(Pile::from_cards(deck), board, drawn, shame, seed, Some(deck_spec.clone())).with_rules(rules);
+
+    // Replay the moves from the original deal, so that the loaded game has
+    // its full history. The result must be the same position as the one that
+    // was saved.
+    let replayed = Replay { seed, deck_spec, rules, actions: moves }.play().map_err(|_| { SaveError::Inconsistent })?;
+    if write_position(&replayed) != write_position(&game) { return Err(SaveError::Inconsistent); }
+    Ok(replayed)
+}
+
+// Parse the first line of a file, which must consist of the given header and
+// a version number, and return the version.
+pub fn parse_header(line: Option<(usize, &str)>, header: &str) -> Result<u32, SaveError> {
+    let (line, text) = line.ok_or(SaveError::Parse { line: 1, message: "File is empty.".to_string() })?;
+    match text.split_whitespace().collect::<Vec<_>>().as_slice() {
+        [word, version] if *word == header => {
+            version.parse().map_err(|_| { SaveError::Parse { line, message: format!("Invalid version: {}", version) } })
+        },
+        _ => Err(SaveError::Parse { line, message: format!("Expected {} header.", header) }),
+    }
+}
+
+pub fn write_action(action: Action) -> String {
+    match action {
+        Action::Draw => "draw".to_string(),
+        Action::Place(pos) => format!("place {} {}", pos.x(), pos.y()),
+        Action::Shame => "shame".to_string(),
+    }
+}
+
+pub fn parse_action(words: &[&str], line: usize) -> Result<Action, SaveError> {
+    match words {
+        ["draw"] => Ok(Action::Draw),
+        ["place", x, y] => Ok(Action::Place(parse_coordinates(&[x, y], line)?)),
+        ["shame"] => Ok(Action::Shame),
+        _ => Err(SaveError::Parse { line, message: format!("Invalid move: {}", words.join(" ")) }),
+    }
+}
+
+pub fn write_deck_spec(spec: &DeckSpec) -> String {
+    if let Some(name) = spec.preset_name() { return name.to_string(); }
+
+    let mut text = format!("decks={} jokers={}", spec.decks, spec.jokers);
+    let mut without: Vec<String> = spec.excluded_values.iter().map(|value| { value.to_string() }).collect();
+    without.extend(spec.excluded_suits.iter().map(|suit| { format!("{:?}", suit).to_lowercase() }));
+    if !without.is_empty() {
+        text.push_str(&format!(" without={}", without.join(",")));
+    }
+    text
+}
+
+pub fn parse_deck_spec(words: &[&str]) -> Result<DeckSpec, String> {
+    if let [name] = words {
+        if !name.contains('=') {
+            return DeckSpec::preset(name).ok_or_else(|| {
+                format!("Unknown deck: {} (try {})", name, DeckSpec::preset_names().join(", "))
+            });
+        }
+    }
+
+    let mut spec = DeckSpec::STANDARD;
+    for word in words {
+        let invalid = || { format!("Invalid deck spec: {}", word) };
+        let mut parts = word.splitn(2, '=');
+        let (key, value) = (parts.next().ok_or_else(invalid)?, parts.next().ok_or_else(invalid)?);
+        match key {
+            "decks" => spec.decks = value.parse().map_err(|_| { invalid() })?,
+            "jokers" => spec.jokers = value.parse().map_err(|_| { invalid() })?,
+            "without" => {
+                for item in value.split(',') {
+                    match item {
+                        "spades" => spec.excluded_suits.push(Suit::Spades),
+                        "hearts" => spec.excluded_suits.push(Suit::Hearts),
+                        "clubs" => spec.excluded_suits.push(Suit::Clubs),
+                        "diamonds" => spec.excluded_suits.push(Suit::Diamonds),
+                        _ => spec.excluded_values.push(item.parse().map_err(|_| { invalid() })?),
+                    }
+                }
+            },
+            _ => return Err(invalid()),
+        }
+    }
+    spec.check().map_err(|reason| { format!("Invalid deck spec: {}", reason) })?;
+    Ok(spec)
+}
+
+pub fn write_rules(rules: &RuleSet) -> String {
+    if let Some(name) = rules.preset_name() { return name.to_string(); }
+
+    let requirement = |requirement| {
+        match requirement {
+            Requirement::Any => "any",
+            Requirement::SameColor => "color",
+            Requirement::SameSuit => "suit",
+        }
+    };
+    let stacking = |stacking| {
+        match stacking {
+            Stacking::ByValue => "value",
+            Stacking::Recycle => "recycle",
+            Stacking::Reset => "reset",
+        }
+    };
+    format!("jacks={} queens={} kings={} equal-stacking={} aces={} jokers={} royals={}",
+            requirement(rules.jacks), requirement(rules.queens), requirement(rules.kings),
+            if rules.equal_stacking { "yes" } else { "no" },
+            stacking(rules.aces), stacking(rules.jokers),
+            match rules.royal_placement { RoyalPlacement::SuitFirst => "suit", RoyalPlacement::ValueFirst => "value" })
+}
+
+pub fn parse_rules(words: &[&str]) -> Result<RuleSet, String> {
+    if let [name] = words {
+        if !name.contains('=') {
+            return RuleSet::preset(name).ok_or_else(|| {
+                format!("Unknown rules: {} (try {})", name, RuleSet::preset_names().join(", "))
+            });
+        }
+    }
+
+    let mut rules = RuleSet::CLASSIC;
+    for word in words {
+        let invalid = || { format!("Invalid rule: {}", word) };
+        let mut parts = word.splitn(2, '=');
+        let (key, value) = (parts.next().ok_or_else(invalid)?, parts.next().ok_or_else(invalid)?);
+        let requirement = || {
+            match value {
+                "any" => Ok(Requirement::Any),
+                "color" => Ok(Requirement::SameColor),
+                "suit" => Ok(Requirement::SameSuit),
+                _ => Err(invalid()),
+            }
+        };
+        let stacking = || {
+            match value {
+                "value" => Ok(Stacking::ByValue),
+                "recycle" => Ok(Stacking::Recycle),
+                "reset" => Ok(Stacking::Reset),
+                _ => Err(invalid()),
+            }
+        };
+        match key {
+            "jacks" => rules.jacks = requirement()?,
+            "queens" => rules.queens = requirement()?,
+            "kings" => rules.kings = requirement()?,
+            "equal-stacking" => rules.equal_stacking = match value { "yes" => true, "no" => false, _ => return Err(invalid()) },
+            "aces" => rules.aces = stacking()?,
+            "jokers" => rules.jokers = stacking()?,
+            "royals" => rules.royal_placement = match value {
+                "suit" => RoyalPlacement::SuitFirst,
+                "value" => RoyalPlacement::ValueFirst,
+                _ => return Err(invalid()),
+            },
+            _ => return Err(invalid()),
+        }
+    }
+    Ok(rules)
+}
+
+fn write_cards(cards: &[Card]) -> String {
+    cards.iter().map(|card| { card.to_string() }).collect::<Vec<_>>().join(" ")
+}
+
+pub fn parse_cards(words: &[&str]) -> Result<Vec<Card>, String> {
+    words.iter().map(|word| { word.parse::<Card>().map_err(|error| { error.to_string() }) }).collect()
+}
+
+// Parse the x and y coordinates of a board position.
+pub fn parse_coordinates(words: &[&str], line: usize) -> Result<BoardPosition, SaveError> {
+    let error = |message: String| { SaveError::Parse { line, message } };
+    match words {
+        [x, y] => {
+            let x = x.parse().map_err(|_| { error(format!("Invalid coordinate: {}", x)) })?;
+            let y = y.parse().map_err(|_| { error(format!("Invalid coordinate: {}", y)) })?;
+            BoardPosition::new((x, y)).map_err(|invalid| { error(invalid.to_string()) })
+        },
+        _ => Err(error("Expected an x and a y coordinate.".to_string())),
+    }
+}
+
+pub fn parse_number<T: std::str::FromStr>(words: &[&str], line: usize) -> Result<T, SaveError> {
+    match words {
+        [word] => word.parse().map_err(|_| { SaveError::Parse { line, message: format!("Invalid number: {}", word) } }),
+        _ => Err(SaveError::Parse { line, message: "Expected a single number.".to_string() }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A game dealt from the given seed, with the first legal move made the
+    // given number of times.
+    fn played_game(seed: u64, moves: usize) -> Game {
+        let mut game = Game::with_seed(seed);
+        game.set_up().unwrap();
+        for _ in 0..moves {
+            let action = game.legal_actions()[0].action();
+            game.apply(action).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn saved_game_loads_with_its_history() {
+        let game = played_game(7, 30);
+        let text = write_game(&game);
+        let mut loaded = parse_game(&text).unwrap();
+        assert_eq!(write_game(&loaded), text);
+        assert_eq!(loaded.history(), game.history());
+
+        loaded.undo();
+        let mut original = game.clone();
+        original.undo();
+        assert_eq!(write_game(&loaded), write_game(&original));
+    }
+
+    #[test]
+    fn moves_must_lead_to_the_saved_position() {
+        let text = write_game(&played_game(7, 4)).replace("shame 0", "shame 2");
+        assert!(matches!(parse_game(&text), Err(SaveError::Inconsistent)));
+    }
+
+    #[test]
+    fn cards_are_written_like_everywhere_else() {
+        let cards = parse_cards(&["AS", "10H", "QD", "JK"]).unwrap();
+        assert_eq!(write_cards(&cards), "AS 10H QD JK");
+        assert!(parse_cards(&["12H"]).is_err());
+        assert!(parse_cards(&["0J"]).is_err());
+    }
+
+    #[test]
+    fn short_lines_are_errors() {
+        let text = write_game(&played_game(7, 0));
+        for line in &["pile 1", "armor 0", "move place 1", "move jump"] {
+            let broken = format!("{}{}\n", text, line);
+            assert!(matches!(parse_game(&broken), Err(SaveError::Parse { .. })), "{}", line);
+        }
+        assert!(parse_coordinates(&["0"], 1).is_err());
+        assert!(parse_coordinates(&["2", "2"], 1).is_err());
+        assert_eq!(parse_coordinates(&["-1", "2"], 1).unwrap(), BoardPosition::new((-1, 2)).unwrap());
+    }
+}
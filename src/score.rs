@@ -0,0 +1,88 @@
+// Scoring, following the rules of Gridcannon: if all royals are killed,
+// the game is won and scored by the number of cards in the shame pile, where
+// fewer is better, with no shame at all being a perfect game. If the game ends
+// in any other way, such as by running out of cards, it is lost, and scored
+// by the number of royals that were killed.
+
+use crate::game::{Game, GameOutcome};
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Score {
+    pub outcome: Option<GameOutcome>,  // None if the game is still in progress
+    pub royals_killed: u8,
+    pub royals_total: u8,  // Twelve, unless the game was dealt from a different deck
+    pub shame: u8,
+}
+
+impl Score {
+    pub fn for_game(game: &Game) -> Score {
+        Score { outcome: game.outcome(), royals_killed: game.royals_killed(), royals_total: game.royals_total(), shame: game.get_shame() }
+    }
+
+    pub fn is_win(&self) -> bool {
+        self.outcome == Some(GameOutcome::Victory)
+    }
+
+    pub fn is_perfect(&self) -> bool {
+        self.is_win() && self.shame == 0
+    }
+
+    // The result of the game in a few words, such as "PERFECT GAME" or "7/12
+    // ROYALS KILLED".
+    pub fn summary(&self) -> String {
+        if self.is_perfect() {
+            "PERFECT GAME".to_string()
+        } else if self.is_win() {
+            format!("WON WITH {} SHAME", self.shame)
+        } else {
+            format!("{}/{} ROYALS KILLED", self.royals_killed, self.royals_total)
+        }
+    }
+
+    // Each of the things that the score is made up of, one per line.
+    pub fn breakdown(&self) -> Vec<String> {
+        vec![
+            format!("{}/{} ROYALS KILLED", self.royals_killed, self.royals_total),
+            format!("{} SHAME", self.shame),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Board, KilledRoyal, Pile};
+
+    // A game with the given number of royals killed out of the given total,
+    // and the given shame, that has no cards left.
+    fn finished_game(killed: u8, total: u8, shame: u8) -> Game {
+        let mut game = Game::from_parts(Pile::new(), Board::new_empty(), None, shame, 0, None);
+        let royal = KilledRoyal { card: "KH".parse().unwrap(), armor: 0, move_number: 0 };
+        game.restore_graveyard(vec![royal; killed as usize], total);
+        game
+    }
+
+    #[test]
+    fn victory_is_scored_by_shame() {
+        let score = finished_game(12, 12, 3).score();
+        assert_eq!(score, Score { outcome: Some(GameOutcome::Victory), royals_killed: 12, royals_total: 12, shame: 3 });
+        assert!(score.is_win() && !score.is_perfect());
+        assert_eq!(score.summary(), "WON WITH 3 SHAME");
+        assert_eq!(score.breakdown(), vec!["12/12 ROYALS KILLED", "3 SHAME"]);
+    }
+
+    #[test]
+    fn victory_without_shame_is_perfect() {
+        let score = finished_game(4, 4, 0).score();
+        assert!(score.is_perfect());
+        assert_eq!(score.summary(), "PERFECT GAME");
+    }
+
+    #[test]
+    fn defeat_is_scored_by_royals_killed() {
+        let score = finished_game(7, 12, 0).score();
+        assert_eq!(score.outcome, Some(GameOutcome::Defeat));
+        assert!(!score.is_win() && !score.is_perfect());
+        assert_eq!(score.summary(), "7/12 ROYALS KILLED");
+    }
+}
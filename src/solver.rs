@@ -0,0 +1,233 @@
+// Solving deals whose deck order is known.
+//
+// The solver searches every sequence of placements and shames that can be
+// played from a position, using the rules of `Game` itself, and finds the line
+// that wins with the least shame. Positions that were already searched with at
+// most as much shame are skipped, which also keeps the search from going around
+// in circles when aces and jokers send piles back into the deck. Since some
+// deals have far too many lines to search them all, the search gives up after
+// a given number of positions.
+
+use std::collections::HashMap;
+
+use crate::game::{Action, BoardPosition, Card, Game, GameOutcome};
+
+// The number of positions that the solver searches by default before giving up.
+pub const DEFAULT_NODE_BUDGET: usize = 200_000;
+
+// The longest line that the solver follows. Aces and jokers can keep sending
+// the same cards back into the deck, so lines could otherwise go on for ever.
+const MAX_MOVES: usize = 400;
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Solution {
+    // The game can be won, and these moves win it with the least shame.
+    Solved { shame: u8, actions: Vec<Action> },
+    // No sequence of moves wins the game.
+    Unwinnable,
+    // The search gave up before it could prove either.
+    Unknown,
+}
+
+struct Search {
+    nodes_left: usize,
+    max_moves: usize,                  // The longest line that is followed
+    complete: bool,                    // False if any line was cut short
+    max_shame: u8,                     // The most shame that a line may end up with
+    too_much_shame: bool,              // True if any line went over the most shame
+    seen: HashMap<Vec<u8>, u8>,        // The least shame each position was searched with
+    line: Vec<Action>,
+}
+
+// Find the way to win the game with the least shame, searching at most
+// `node_budget` positions. The moves start from the current position of the
+// game.
+pub fn solve(game: &Game, node_budget: usize) -> Solution {
+    solve_within(game, node_budget, MAX_MOVES)
+}
+
+// Like `solve`, but cut lines short after the given number of moves.
+fn solve_within(game: &Game, node_budget: usize, max_moves: usize) -> Solution {
+    let mut game = game.clone();
+    game.forget_history();
+
+    // Look for a win without any more shame first, then allow one more card
+    // to be shamed at a time, so that the first win found has the least shame.
+    // Once no line needs more shame than allowed, there is nothing left to try.
+    let mut search = Search {
+        nodes_left: node_budget,
+        max_moves,
+        complete: true,
+        max_shame: game.get_shame(),
+        too_much_shame: false,
+        seen: HashMap::new(),
+        line: vec![],
+    };
+    loop {
+        search.seen.clear();
+        search.too_much_shame = false;
+        if search.visit(&game) { return Solution::Solved { shame: search.max_shame, actions: search.line }; }
+        // A line that was cut short might still have won with this much shame,
+        // so a win found with more shame would not be known to need the least
+        if !search.complete { return Solution::Unknown; }
+        if !search.too_much_shame { return Solution::Unwinnable; }
+        if search.max_shame == u8::MAX { return Solution::Unknown; }
+        search.max_shame += 1;
+    }
+}
+
+impl Search {
+    // Search every line from the given position that stays within the most
+    // shame, returning whether one of them wins. The winning line is left in
+    // `line`.
+    fn visit(&mut self, game: &Game) -> bool {
+        if game.royals_killed() >= game.royals_total() { return true; }
+        if game.get_shame() > self.max_shame {
+            self.too_much_shame = true;
+            return false;
+        }
+        if let Some(GameOutcome::Defeat) | Some(GameOutcome::Stuck) = game.outcome() { return false; }
+
+        if self.nodes_left == 0 || self.line.len() >= self.max_moves {
+            self.complete = false;
+            return false;
+        }
+
+        let key = position_key(game);
+        match self.seen.get(&key) {
+            Some(shame) if *shame <= game.get_shame() => return false,
+            _ => { self.seen.insert(key, game.get_shame()); },
+        }
+        self.nodes_left -= 1;
+
+        for (action, next) in ordered_actions(game) {
+            self.line.push(action);
+            if self.visit(&next) { return true; }
+            self.line.pop();
+        }
+        false
+    }
+}
+
+// The moves worth trying from a position, most promising first.
+fn ordered_actions(game: &Game) -> Vec<(Action, Game)> {
+    let mut moves: Vec<(i32, Action, Game)> = game.legal_actions().into_iter().filter_map(|legal| {
+        let mut next = game.clone();
+        next.perform(legal.action()).ok()?;
+        Some((promise(&next), legal.action(), next))
+    }).collect();
+    moves.sort_by(|(a, _, _), (b, _, _)| { b.cmp(a) });
+    moves.into_iter().map(|(_, action, next)| { (action, next) }).collect()
+}
+
+// Guess how close a position is to being won: every royal that was killed
+// counts for a lot, and every royal on the board counts against it by how much
+// more damage its cannon needs to deal to kill it. Shame and the cards left in
+// the deck count against it a little, so that lines which keep sending the same
+// cards back into the deck are tried last.
+pub(crate) fn promise(game: &Game) -> i32 {
+    let missing: i32 = game.royal_statuses().iter().map(|status| {
+        let damage: u8 = status.position.cannon_towards().into_iter()
+            .filter_map(|pos| { game.get_card_at(pos) })
+            .filter(|card| { status.requirement.is_met_by(*card, status.card) })
+            .map(|card| { card.value() })
+            .sum();
+        (status.health() as i32 - damage as i32).max(0)
+    }).sum();
+    game.royals_killed() as i32 * 100 - missing - game.get_shame() as i32 - game.cards_left() as i32
+}
+
+// Encode everything about a position except the shame, so that positions that
+// were reached in different ways can be recognised.
+fn position_key(game: &Game) -> Vec<u8> {
+    let code = |card: Card| { card.value() * 5 + card.suit() as u8 };
+    let mut key = vec![game.royals_killed(), game.drawn().map_or(255, code)];
+    key.extend(game.deck().cards().iter().map(|card| { code(*card) }));
+    for pos in BoardPosition::all_valid() {
+        key.push(255);
+        key.push(game.get_armor_at(pos));
+        key.extend(game.board().get_pile_at(pos).cards().iter().map(|card| { code(*card) }));
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::{Board, Pile};
+    use crate::rules::RuleSet;
+
+    // A game whose cannon is dealt 2C to 8C and 9S, with the given cards left
+    // in the deck, the last of them on top.
+    fn dealt_game(deck: &str) -> Game {
+        Game::from_deck_order(format!("{} 9S 8C 7C 6C 5C 4C 3C 2C", deck).parse().unwrap()).unwrap()
+    }
+
+    fn play(game: &Game, actions: &[Action]) -> Game {
+        let mut game = game.clone();
+        for action in actions {
+            game.apply(*action).unwrap();
+        }
+        game
+    }
+
+    #[test]
+    fn finds_a_win_without_shame() {
+        let game = dealt_game("10H JS");
+        match solve(&game, DEFAULT_NODE_BUDGET) {
+            Solution::Solved { shame, actions } => {
+                assert_eq!((shame, actions.len()), (0, 4));
+                let end = play(&game, &actions);
+                assert_eq!((end.outcome(), end.get_shame()), (Some(GameOutcome::Victory), 0));
+            },
+            solution => panic!("Expected a solution, but got {:?}", solution),
+        }
+    }
+
+    #[test]
+    fn finds_the_least_shame_when_some_is_needed() {
+        // Without stacking on equal cards, only one of the twos fits on the
+        // centre, and the other one has to be shamed
+        let game = dealt_game("10H JS 2D 2H").with_rules(RuleSet::STRICT);
+        match solve(&game, DEFAULT_NODE_BUDGET) {
+            Solution::Solved { shame, actions } => {
+                assert_eq!(shame, 1);
+                let end = play(&game, &actions);
+                assert_eq!((end.outcome(), end.get_shame()), (Some(GameOutcome::Victory), 1));
+            },
+            solution => panic!("Expected a solution, but got {:?}", solution),
+        }
+    }
+
+    #[test]
+    fn proves_a_deal_unwinnable() {
+        // The two fits nowhere that fires a cannon at the Jack
+        assert_eq!(solve(&dealt_game("2H JS"), DEFAULT_NODE_BUDGET), Solution::Unwinnable);
+    }
+
+    #[test]
+    fn lines_cut_short_leave_the_least_shame_unknown() {
+        // Shaming the 8H and firing the 10H at the Jack wins in four moves.
+        // Without shame, the 8H has to go on the Jack as armor, and it takes
+        // two more moves to load the cannon enough to kill it.
+        let mut board = Board::new_empty();
+        for pos in BoardPosition::all_valid() {
+            let card = match (pos.x(), pos.y()) {
+                (0, 0) => "9C",
+                (1, 0) => "9S",
+                (2, 0) => "JS",
+                _ if pos.is_cannon() => "10D",
+                _ => continue,
+            };
+            board.place_card_at(pos, card.parse().unwrap());
+        }
+        let game = Game::from_parts("10C 10S 10H 8H".parse::<Pile>().unwrap(), board, None, 0, 0, None);
+        assert!(matches!(solve_within(&game, DEFAULT_NODE_BUDGET, 6), Solution::Solved { shame: 0, .. }));
+        assert_eq!(solve_within(&game, DEFAULT_NODE_BUDGET, 5), Solution::Unknown);
+    }
+
+    #[test]
+    fn gives_up_at_the_search_limit() {
+        assert_eq!(solve(&dealt_game("10H JS"), 1), Solution::Unknown);
+    }
+}
@@ -0,0 +1,202 @@
+// Statistics about every game the player has finished, kept between runs.
+//
+// They are stored in `stats.txt` in the data directory, as plain text with one
+// number per line:
+//
+//     squaretillery-stats 1
+//     played 10
+//     victories 2
+//     perfect 1
+//     shame 34
+//     royals-killed 75
+//     streak 1
+//     best-streak 2
+//
+// Shame and royals killed are totals over all games, from which the averages
+// are worked out. The streak is the number of victories in a row up to the
+// last game.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::paths;
+use crate::save::{parse_header, parse_number, SaveError};
+use crate::score::Score;
+
+const HEADER: &str = "squaretillery-stats";
+const VERSION: u32 = 1;
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Stats {
+    pub played: u32,
+    pub victories: u32,
+    pub perfect: u32,        // Victories without any shame
+    pub shame: u32,          // Total shame over all games
+    pub royals_killed: u32,  // Total royals killed over all games
+    pub streak: u32,         // Victories in a row, up to the last game
+    pub best_streak: u32,
+}
+
+impl Stats {
+    // Count a finished game. The counters stop at their largest value rather
+    // than overflowing, since the file they are read from can be edited.
+    pub fn record(&mut self, score: &Score) {
+        self.played = self.played.saturating_add(1);
+        self.shame = self.shame.saturating_add(score.shame as u32);
+        self.royals_killed = self.royals_killed.saturating_add(score.royals_killed as u32);
+        if score.is_win() {
+            self.victories = self.victories.saturating_add(1);
+            self.streak = self.streak.saturating_add(1);
+            self.best_streak = self.best_streak.max(self.streak);
+        } else {
+            self.streak = 0;
+        }
+        if score.is_perfect() {
+            self.perfect = self.perfect.saturating_add(1);
+        }
+    }
+
+    pub fn average_shame(&self) -> f64 {
+        if self.played == 0 { 0.0 } else { self.shame as f64 / self.played as f64 }
+    }
+
+    pub fn average_royals_killed(&self) -> f64 {
+        if self.played == 0 { 0.0 } else { self.royals_killed as f64 / self.played as f64 }
+    }
+
+    // The statistics to show to the player, one per line.
+    pub fn lines(&self) -> Vec<String> {
+        let percentage = if self.played == 0 { 0.0 } else { self.victories as f64 / self.played as f64 * 100.0 };
+        vec![
+            format!("{} GAMES PLAYED", self.played),
+            format!("{} VICTORIES ({:.0}%)", self.victories, percentage),
+            format!("{} PERFECT GAMES", self.perfect),
+            format!("{:.1} SHAME PER GAME", self.average_shame()),
+            format!("{:.1} ROYALS KILLED PER GAME", self.average_royals_killed()),
+            format!("WIN STREAK {} (BEST {})", self.streak, self.best_streak),
+        ]
+    }
+}
+
+// The file that the statistics are kept in.
+pub fn path() -> Option<PathBuf> {
+    paths::data_dir().map(|dir| dir.join("stats.txt"))
+}
+
+// Load the statistics, which are all zero if no game was finished yet.
+pub fn load(path: &Path) -> Result<Stats, SaveError> {
+    if !path.exists() { return Ok(Stats::default()); }
+    parse_stats(&fs::read_to_string(path)?)
+}
+
+pub fn save(stats: &Stats, path: &Path) -> Result<(), SaveError> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, write_stats(stats))?;
+    Ok(())
+}
+
+// Count a finished game in the statistics on disk, and return the new
+// statistics.
+pub fn record(path: &Path, score: &Score) -> Result<Stats, SaveError> {
+    let mut stats = load(path)?;
+    stats.record(score);
+    save(&stats, path)?;
+    Ok(stats)
+}
+
+pub fn write_stats(stats: &Stats) -> String {
+    let lines = [
+        format!("{} {}", HEADER, VERSION),
+        format!("played {}", stats.played),
+        format!("victories {}", stats.victories),
+        format!("perfect {}", stats.perfect),
+        format!("shame {}", stats.shame),
+        format!("royals-killed {}", stats.royals_killed),
+        format!("streak {}", stats.streak),
+        format!("best-streak {}", stats.best_streak),
+    ];
+    lines.join("\n") + "\n"
+}
+
+pub fn parse_stats(text: &str) -> Result<Stats, SaveError> {
+    let mut lines = text.lines().enumerate().map(|(i, line)| (i + 1, line));
+
+    let version = parse_header(lines.next(), HEADER)?;
+    if version != VERSION { return Err(SaveError::UnsupportedVersion(version)); }
+
+    let mut stats = Stats::default();
+    for (line, text) in lines {
+        let words: Vec<&str> = text.split_whitespace().collect();
+        match words.split_first() {
+            None => {},
+            Some((&"played", rest)) => stats.played = parse_number(rest, line)?,
+            Some((&"victories", rest)) => stats.victories = parse_number(rest, line)?,
+            Some((&"perfect", rest)) => stats.perfect = parse_number(rest, line)?,
+            Some((&"shame", rest)) => stats.shame = parse_number(rest, line)?,
+            Some((&"royals-killed", rest)) => stats.royals_killed = parse_number(rest, line)?,
+            Some((&"streak", rest)) => stats.streak = parse_number(rest, line)?,
+            Some((&"best-streak", rest)) => stats.best_streak = parse_number(rest, line)?,
+            Some((keyword, _)) => return Err(SaveError::Parse { line, message: format!("Unknown keyword: {}", keyword) }),
+        }
+    }
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::GameOutcome;
+
+    fn score(outcome: GameOutcome, royals_killed: u8, shame: u8) -> Score {
+        Score { outcome: Some(outcome), royals_killed, royals_total: 12, shame }
+    }
+
+    #[test]
+    fn losses_end_the_streak_but_not_the_best_one() {
+        let mut stats = Stats::default();
+        stats.record(&score(GameOutcome::Victory, 12, 2));
+        stats.record(&score(GameOutcome::Victory, 12, 0));
+        stats.record(&score(GameOutcome::Defeat, 5, 4));
+        stats.record(&score(GameOutcome::Victory, 12, 1));
+        assert_eq!(stats, Stats { played: 4, victories: 3, perfect: 1, shame: 7, royals_killed: 41, streak: 1, best_streak: 2 });
+    }
+
+    #[test]
+    fn perfect_games_are_also_victories() {
+        let mut stats = Stats::default();
+        stats.record(&score(GameOutcome::Victory, 12, 0));
+        assert_eq!((stats.victories, stats.perfect, stats.streak), (1, 1, 1));
+    }
+
+    #[test]
+    fn counters_stop_at_their_largest_value() {
+        let mut stats = parse_stats("squaretillery-stats 1\nplayed 4294967295\nshame 4294967295\n").unwrap();
+        stats.record(&score(GameOutcome::Defeat, 3, 2));
+        assert_eq!((stats.played, stats.shame, stats.royals_killed), (u32::MAX, u32::MAX, 3));
+    }
+
+    #[test]
+    fn stats_round_trip() {
+        let stats = Stats { played: 10, victories: 2, perfect: 1, shame: 34, royals_killed: 75, streak: 1, best_streak: 2 };
+        let text = write_stats(&stats);
+        assert_eq!(parse_stats(&text).unwrap(), stats);
+        assert_eq!(write_stats(&parse_stats(&text).unwrap()), text);
+    }
+
+    #[test]
+    fn unknown_keywords_and_versions_are_errors() {
+        assert!(matches!(parse_stats("squaretillery-stats 1\nlosses 3\n"), Err(SaveError::Parse { line: 2, .. })));
+        assert!(matches!(parse_stats("squaretillery-stats 2\nplayed 3\n"), Err(SaveError::UnsupportedVersion(2))));
+    }
+
+    #[test]
+    fn no_games_gives_zeros_rather_than_dividing_by_zero() {
+        let stats = Stats::default();
+        assert_eq!((stats.average_shame(), stats.average_royals_killed()), (0.0, 0.0));
+        assert_eq!(stats.lines()[1], "0 VICTORIES (0%)");
+        assert_eq!(stats.lines()[3], "0.0 SHAME PER GAME");
+        assert_eq!(stats.lines()[4], "0.0 ROYALS KILLED PER GAME");
+    }
+}